@@ -0,0 +1,107 @@
+use super::Client;
+use desec_api::account::{AccountInformation, Captcha, Login, RegisterResponse};
+use desec_api::Error;
+use reqwest::Method;
+use serde_json::json;
+
+/// Requests for the account the client is authenticated for.
+pub struct AccountClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn account(&self) -> AccountClient<'_> {
+        AccountClient { client: self }
+    }
+}
+
+impl<'a> AccountClient<'a> {
+    pub async fn get_account_info(&self) -> Result<AccountInformation, Error> {
+        self.client.json(Method::GET, "/auth/account/", None).await
+    }
+
+    pub async fn update_outreach_preference(
+        &self,
+        outreach_preference: bool,
+    ) -> Result<AccountInformation, Error> {
+        self.client
+            .json(
+                Method::PATCH,
+                "/auth/account/",
+                Some(json!({ "outreach_preference": outreach_preference })),
+            )
+            .await
+    }
+}
+
+// The requests below work without token, they use an unauthenticated client.
+
+pub async fn get_captcha() -> Result<Captcha, Error> {
+    Client::unauthenticated()?
+        .json(Method::POST, "/captcha/", None)
+        .await
+}
+
+pub async fn register(
+    email: &str,
+    password: &str,
+    captcha_id: &str,
+    captcha_solution: &str,
+    domain: Option<&str>,
+) -> Result<RegisterResponse, Error> {
+    let mut payload = json!({
+        "email": email,
+        "password": password,
+        "captcha": {
+            "id": captcha_id,
+            "solution": captcha_solution
+        }
+    });
+    if let Some(domain) = domain {
+        payload["domain"] = json!(domain);
+    }
+    Client::unauthenticated()?
+        .json(Method::POST, "/auth/", Some(payload))
+        .await
+}
+
+pub async fn login(email: &str, password: &str) -> Result<Login, Error> {
+    Client::unauthenticated()?
+        .json(
+            Method::POST,
+            "/auth/login/",
+            Some(json!({ "email": email, "password": password })),
+        )
+        .await
+}
+
+pub async fn request_password_reset(
+    email: &str,
+    captcha_id: &str,
+    captcha_solution: &str,
+) -> Result<(), Error> {
+    let payload = json!({
+        "email": email,
+        "captcha": {
+            "id": captcha_id,
+            "solution": captcha_solution
+        }
+    });
+    Client::unauthenticated()?
+        .send(Method::POST, "/auth/account/reset-password/", Some(payload))
+        .await
+        .map(|_| ())
+}
+
+pub async fn confirm_password_reset(
+    new_password: &str,
+    code: &str,
+) -> Result<AccountInformation, Error> {
+    Client::unauthenticated()?
+        .json(
+            Method::POST,
+            &format!("/auth/account/reset-password/{code}"),
+            Some(json!({ "new_password": new_password })),
+        )
+        .await
+}
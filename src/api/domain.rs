@@ -0,0 +1,55 @@
+use super::Client;
+use desec_api::domain::Domain;
+use desec_api::Error;
+use reqwest::Method;
+use serde_json::json;
+
+/// Requests for the domains of the account.
+pub struct DomainClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn domain(&self) -> DomainClient<'_> {
+        DomainClient { client: self }
+    }
+}
+
+impl<'a> DomainClient<'a> {
+    pub async fn create_domain(&self, domain: &str) -> Result<Domain, Error> {
+        self.client
+            .json(Method::POST, "/domains/", Some(json!({ "name": domain })))
+            .await
+    }
+
+    pub async fn get_domains(&self) -> Result<Vec<Domain>, Error> {
+        self.client.json(Method::GET, "/domains/", None).await
+    }
+
+    pub async fn get_domain(&self, domain: &str) -> Result<Domain, Error> {
+        self.client
+            .json(Method::GET, &format!("/domains/{domain}/"), None)
+            .await
+    }
+
+    pub async fn delete_domain(&self, domain: &str) -> Result<(), Error> {
+        self.client
+            .send(Method::DELETE, &format!("/domains/{domain}/"), None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns the domain of the account which the name belongs to, if any.
+    pub async fn get_owning_domain(&self, qname: &str) -> Result<Vec<Domain>, Error> {
+        self.client
+            .json(Method::GET, &format!("/domains/?owns_qname={qname}"), None)
+            .await
+    }
+
+    /// Returns the zone file of the domain as rendered by deSEC.
+    pub async fn get_zonefile(&self, domain: &str) -> Result<String, Error> {
+        self.client
+            .send(Method::GET, &format!("/domains/{domain}/zonefile/"), None)
+            .await
+    }
+}
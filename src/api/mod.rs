@@ -0,0 +1,328 @@
+//! Client for the deSEC API, using the data types and errors of desec_api.
+//!
+//! All requests go through a single [`Client`], so they are logged and traced the same way
+//! and throttled requests are retried the same way desec_api does it. desec_api's own client
+//! cannot be used for this, it neither exposes the responses of retried attempts nor
+//! sends bulk requests or explicit nulls.
+
+pub mod account;
+pub mod domain;
+pub mod rrset;
+pub mod token;
+pub mod trace;
+
+use desec_api::{Error, API_URL, USERAGENT};
+use log::{debug, log_enabled, trace, Level};
+use reqwest::{header, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::time::{Instant, SystemTime};
+use tokio::time::{sleep, Duration};
+
+/// Allowed subnets of a token which has not been restricted to specific networks.
+pub const DEFAULT_ALLOWED_SUBNETS: [&str; 2] = ["0.0.0.0/0", "::/0"];
+
+/// A client for the deSEC API, authenticated with a token unless created for
+/// the account requests which work without, like login or registration.
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: reqwest::Client,
+    /// Authorization header with the token, none for unauthenticated clients
+    authorization: Option<header::HeaderValue>,
+    /// Wheter to retry throttled requests based on the retry header
+    retry: bool,
+    /// Maximum waiting time to accept on a single retry
+    max_wait_retry: u64,
+    /// Maximum number of retries
+    max_retries: usize,
+}
+
+/// A single field of a PATCH request.
+///
+/// Fields are either left untouched, set to a new value or cleared,
+/// which resets them to the default of the API.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Patch<T> {
+    #[default]
+    Unchanged,
+    Set(T),
+    Clear,
+}
+
+impl<T> Patch<T> {
+    /// Builds the patch from an optional value and a `--clear-<field>` flag.
+    pub fn new(value: Option<T>, clear: bool) -> Self {
+        match (value, clear) {
+            (Some(value), _) => Patch::Set(value),
+            (None, true) => Patch::Clear,
+            (None, false) => Patch::Unchanged,
+        }
+    }
+
+    /// The new value of the field, given its current value.
+    pub fn resolve(self, current: Option<T>) -> Option<T> {
+        match self {
+            Patch::Unchanged => current,
+            Patch::Set(value) => Some(value),
+            Patch::Clear => None,
+        }
+    }
+}
+
+impl<T: Into<Value>> Patch<T> {
+    /// Adds the field to the given payload unless it is unchanged.
+    /// Cleared fields are sent as null.
+    pub fn apply(self, payload: &mut Map<String, Value>, key: &str) {
+        match self {
+            Patch::Unchanged => (),
+            Patch::Set(value) => {
+                payload.insert(key.to_string(), value.into());
+            }
+            Patch::Clear => {
+                payload.insert(key.to_string(), Value::Null);
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Creates a new client using the given API token.
+    pub fn new(token: &str) -> Result<Self, Error> {
+        let mut authorization = header::HeaderValue::from_str(format!("Token {token}").as_str())
+            .map_err(|error| Error::ReqwestClientBuilder(error.to_string()))?;
+        authorization.set_sensitive(true);
+        let mut client = Client::unauthenticated()?;
+        client.authorization = Some(authorization);
+        Ok(client)
+    }
+
+    /// Creates a client without token for login, registration and password resets.
+    pub fn unauthenticated() -> Result<Self, Error> {
+        let client = reqwest::ClientBuilder::new()
+            .build()
+            .map_err(|error| Error::ReqwestClientBuilder(error.to_string()))?;
+        Ok(Client {
+            client,
+            authorization: None,
+            retry: true,
+            max_wait_retry: 60,
+            max_retries: 3,
+        })
+    }
+
+    /// Sets whether retries are enabled.
+    pub fn set_retry(&mut self, retry: bool) {
+        self.retry = retry;
+    }
+
+    /// Sets the maximum wait time for a single retry
+    pub fn set_max_wait_retry(&mut self, max_wait_retry: u64) {
+        self.max_wait_retry = max_wait_retry;
+    }
+
+    /// Sets the maximum number of retries
+    pub fn set_max_retries(&mut self, max_retries: usize) {
+        self.max_retries = max_retries;
+    }
+
+    /// Sends the request and parses the JSON response body.
+    async fn json<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<Value>,
+    ) -> Result<T, Error> {
+        let response_text = self.send(method, endpoint, body).await?;
+        serde_json::from_str(&response_text)
+            .map_err(|error| Error::InvalidAPIResponse(error.to_string(), response_text))
+    }
+
+    /// Sends the request and returns the response body.
+    /// If a status code 429 is encountered, depending on the configuration, retries are done.
+    /// Every attempt is recorded for `--trace-http` with the response as received.
+    async fn send(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<Value>,
+    ) -> Result<String, Error> {
+        let url = format!("{API_URL}{endpoint}");
+        // Headers are set on the request instead of the client, so they are part of the trace
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header(header::USER_AGENT, USERAGENT);
+        if let Some(authorization) = &self.authorization {
+            request = request.header(header::AUTHORIZATION, authorization.clone());
+        }
+        if let Some(body) = body {
+            if log_enabled!(Level::Trace) {
+                trace!("Request body: {}", redact(body.clone()));
+            }
+            request = request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.to_string());
+        }
+        let request = request.build().map_err(Error::Reqwest)?;
+        let mut retries: usize = 0;
+        loop {
+            if retries > self.max_retries {
+                debug!("Giving up after {} retries", self.max_retries);
+                return Err(Error::RateLimitedMaxRetriesReached);
+            }
+            let started = SystemTime::now();
+            let attempt = Instant::now();
+            let response = match self
+                .client
+                .execute(
+                    request
+                        .try_clone()
+                        .expect("this request should always be clonable"),
+                )
+                .await
+            {
+                Ok(response) => response,
+                Err(error) => {
+                    debug!("{method} {url} failed: {error}");
+                    trace::record(&request, started, attempt.elapsed(), retries, Err(&error));
+                    return Err(Error::Reqwest(error));
+                }
+            };
+            let status = response.status();
+            let headers = response.headers().clone();
+            let text = match response.text().await {
+                Ok(text) => text,
+                Err(error) => {
+                    debug!("{method} {url} {status} failed to read the body: {error}");
+                    trace::record(&request, started, attempt.elapsed(), retries, Err(&error));
+                    return Err(Error::Reqwest(error));
+                }
+            };
+            debug!(
+                "{method} {url} {status} in {} ms",
+                attempt.elapsed().as_millis()
+            );
+            trace::record(
+                &request,
+                started,
+                attempt.elapsed(),
+                retries,
+                Ok((status, &headers, &text)),
+            );
+            if log_enabled!(Level::Trace) {
+                match serde_json::from_str(&text) {
+                    Ok(body) => trace!("Response body: {}", redact(body)),
+                    Err(_) => trace!("Response body: {text}"),
+                }
+            }
+            match status {
+                StatusCode::OK
+                | StatusCode::CREATED
+                | StatusCode::NO_CONTENT
+                | StatusCode::ACCEPTED => return Ok(text),
+                StatusCode::TOO_MANY_REQUESTS => {
+                    let ttw = self.time_to_wait(&headers, text)?;
+                    debug!("Request has been throttled, we wait {ttw} seconds");
+                    sleep(Duration::from_secs(ttw)).await;
+                    retries += 1;
+                }
+                StatusCode::UNAUTHORIZED => return Err(Error::Unauthorized(text)),
+                StatusCode::FORBIDDEN => return Err(Error::Forbidden),
+                StatusCode::BAD_REQUEST => return Err(Error::ApiError(status.as_u16(), text)),
+                StatusCode::NOT_FOUND => return Err(Error::NotFound),
+                _ => return Err(Error::UnexpectedStatusCode(status.into(), text)),
+            }
+        }
+    }
+
+    // Parsing the time we have to wait till next retry.
+    // Error out if we cannot parse, retry is disabled, or accepted max wait time will be exceeded.
+    fn time_to_wait(&self, headers: &header::HeaderMap, text: String) -> Result<u64, Error> {
+        let time_to_wait: u64 = headers
+            .get("retry-after")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.parse().ok())
+            .ok_or_else(|| {
+                Error::RateLimitedWithoutRetry(
+                    "Request got throttled without a valid retry-after header".to_string(),
+                )
+            })?;
+        if !self.retry {
+            return Err(Error::RateLimited(time_to_wait, text));
+        }
+        if time_to_wait > self.max_wait_retry {
+            return Err(Error::RateLimited(
+                time_to_wait,
+                format!(
+                    "Wait time for retry {} exceeds max accepted wait time per retry {}",
+                    time_to_wait, self.max_wait_retry
+                ),
+            ));
+        }
+        Ok(time_to_wait)
+    }
+}
+
+/// Masks passwords and tokens in bodies before they are logged or traced,
+/// including nested objects and lists like the token list.
+fn redact(mut body: Value) -> Value {
+    match &mut body {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match ["password", "new_password", "token"].contains(&key.as_str())
+                    && value.is_string()
+                {
+                    true => *value = Value::from("***"),
+                    false => *value = redact(value.take()),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                *value = redact(value.take());
+            }
+        }
+        _ => {}
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redact_nested() {
+        let body = json!({
+            "email": "admin@example.com",
+            "password": "secret",
+            "captcha": {"id": "id", "solution": "solution"},
+            "login": {"token": "secret", "name": "login"},
+        });
+        assert_eq!(
+            redact(body),
+            json!({
+                "email": "admin@example.com",
+                "password": "***",
+                "captcha": {"id": "id", "solution": "solution"},
+                "login": {"token": "***", "name": "login"},
+            })
+        );
+    }
+
+    #[test]
+    fn redact_lists() {
+        let body = json!([
+            {"id": "1", "token": "secret"},
+            {"id": "2", "name": "ci"},
+        ]);
+        assert_eq!(
+            redact(body),
+            json!([
+                {"id": "1", "token": "***"},
+                {"id": "2", "name": "ci"},
+            ])
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use super::Client;
+use crate::changeset::RrsetState;
+use desec_api::rrset::ResourceRecordSet;
+use desec_api::Error;
+use reqwest::Method;
+use serde_json::json;
+
+/// Requests for the rrsets of a domain.
+///
+/// A subname of None stands for the zone apex.
+pub struct RrsetClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn rrset(&self) -> RrsetClient<'_> {
+        RrsetClient { client: self }
+    }
+}
+
+// https://desec.readthedocs.io/en/latest/dns/rrsets.html#accessing-the-zone-apex
+fn path(domain: &str, subname: Option<&str>, rrset_type: &str) -> String {
+    let subname = subname.unwrap_or("@");
+    format!("/domains/{domain}/rrsets/{subname}/{rrset_type}/")
+}
+
+impl<'a> RrsetClient<'a> {
+    pub async fn create_rrset(
+        &self,
+        domain: &str,
+        subname: Option<&str>,
+        rrset_type: &str,
+        ttl: u64,
+        records: &[String],
+    ) -> Result<ResourceRecordSet, Error> {
+        let rrset = json!({
+            "subname": subname.unwrap_or_default(),
+            "type": rrset_type,
+            "ttl": ttl,
+            "records": records
+        });
+        self.client
+            .json(
+                Method::POST,
+                &format!("/domains/{domain}/rrsets/"),
+                Some(rrset),
+            )
+            .await
+    }
+
+    pub async fn get_rrsets(&self, domain: &str) -> Result<Vec<ResourceRecordSet>, Error> {
+        self.client
+            .json(Method::GET, &format!("/domains/{domain}/rrsets/"), None)
+            .await
+    }
+
+    pub async fn get_rrsets_by_type(
+        &self,
+        domain: &str,
+        r#type: &str,
+    ) -> Result<Vec<ResourceRecordSet>, Error> {
+        self.client
+            .json(
+                Method::GET,
+                &format!("/domains/{domain}/rrsets/?type={type}", type = r#type),
+                None,
+            )
+            .await
+    }
+
+    pub async fn get_rrsets_by_subname(
+        &self,
+        domain: &str,
+        subname: &str,
+    ) -> Result<Vec<ResourceRecordSet>, Error> {
+        self.client
+            .json(
+                Method::GET,
+                &format!("/domains/{domain}/rrsets/?subname={subname}"),
+                None,
+            )
+            .await
+    }
+
+    pub async fn get_rrset(
+        &self,
+        domain: &str,
+        subname: Option<&str>,
+        rrset_type: &str,
+    ) -> Result<ResourceRecordSet, Error> {
+        self.client
+            .json(Method::GET, &path(domain, subname, rrset_type), None)
+            .await
+    }
+
+    /// Deletes the rrset, which also succeeds if it does not exist.
+    pub async fn delete_rrset(
+        &self,
+        domain: &str,
+        subname: Option<&str>,
+        rrset_type: &str,
+    ) -> Result<(), Error> {
+        self.client
+            .send(Method::DELETE, &path(domain, subname, rrset_type), None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Creates, updates and deletes multiple rrsets of a domain in a single atomic request.
+    pub async fn patch_rrsets(
+        &self,
+        domain: &str,
+        rrsets: &[RrsetState],
+    ) -> Result<Vec<ResourceRecordSet>, Error> {
+        let body =
+            serde_json::to_value(rrsets).map_err(|error| Error::Serialize(error.to_string()))?;
+        self.client
+            .json(
+                Method::PATCH,
+                &format!("/domains/{domain}/rrsets/"),
+                Some(body),
+            )
+            .await
+    }
+}
@@ -0,0 +1,213 @@
+use super::{Client, Patch};
+use desec_api::token::{Token, TokenPolicy};
+use desec_api::Error;
+use reqwest::Method;
+use serde_json::{json, Map, Value};
+
+/// Requests for the tokens of the account and their policies.
+pub struct TokenClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn token(&self) -> TokenClient<'_> {
+        TokenClient { client: self }
+    }
+}
+
+/// The fields of a token to create or patch, fields which are None or unchanged are not sent.
+#[derive(Clone, Debug, Default)]
+pub struct TokenFields {
+    pub name: Option<String>,
+    pub allowed_subnets: Option<Vec<String>>,
+    pub perm_manage_tokens: Option<bool>,
+    /// Cleared to remove the limit, sent as null
+    pub max_age: Patch<String>,
+    /// Cleared to remove the limit, sent as null
+    pub max_unused_period: Patch<String>,
+}
+
+impl TokenFields {
+    /// The request body sent for the fields.
+    pub fn body(&self) -> Value {
+        let fields = [
+            ("name", self.name.clone().map(Value::from)),
+            (
+                "allowed_subnets",
+                self.allowed_subnets.clone().map(Value::from),
+            ),
+            (
+                "perm_manage_tokens",
+                self.perm_manage_tokens.map(Value::from),
+            ),
+        ];
+        let mut body: Map<String, Value> = fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect();
+        self.max_age.clone().apply(&mut body, "max_age");
+        self.max_unused_period
+            .clone()
+            .apply(&mut body, "max_unused_period");
+        Value::Object(body)
+    }
+}
+
+/// The fields of a token policy, None for domain, subname or type matches all of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyFields {
+    pub domain: Option<String>,
+    pub subname: Option<String>,
+    pub r#type: Option<String>,
+    pub perm_write: bool,
+}
+
+impl PolicyFields {
+    /// The request body sent for the fields, all of them are sent.
+    pub fn body(&self) -> Value {
+        json!({
+            "domain": self.domain,
+            "subname": self.subname,
+            "type": self.r#type,
+            "perm_write": self.perm_write,
+        })
+    }
+}
+
+impl From<&TokenPolicy> for PolicyFields {
+    fn from(policy: &TokenPolicy) -> Self {
+        PolicyFields {
+            domain: policy.domain.clone(),
+            subname: policy.subname.clone(),
+            r#type: policy.r#type.clone(),
+            perm_write: policy.perm_write,
+        }
+    }
+}
+
+impl<'a> TokenClient<'a> {
+    /// Creates a token, fields which are not given get the default of the API.
+    pub async fn create(&self, fields: TokenFields) -> Result<Token, Error> {
+        self.client
+            .json(Method::POST, "/auth/tokens/", Some(fields.body()))
+            .await
+    }
+
+    pub async fn delete(&self, token_id: &str) -> Result<(), Error> {
+        self.client
+            .send(Method::DELETE, &format!("/auth/tokens/{token_id}/"), None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn list(&self) -> Result<Vec<Token>, Error> {
+        self.client.json(Method::GET, "/auth/tokens/", None).await
+    }
+
+    pub async fn get(&self, token_id: &str) -> Result<Token, Error> {
+        self.client
+            .json(Method::GET, &format!("/auth/tokens/{token_id}/"), None)
+            .await
+    }
+
+    /// Updates the given fields of the token.
+    pub async fn patch(&self, token_id: &str, fields: TokenFields) -> Result<Token, Error> {
+        self.client
+            .json(
+                Method::PATCH,
+                &format!("/auth/tokens/{token_id}/"),
+                Some(fields.body()),
+            )
+            .await
+    }
+
+    pub async fn create_policy(
+        &self,
+        token_id: &str,
+        fields: PolicyFields,
+    ) -> Result<TokenPolicy, Error> {
+        self.client
+            .json(
+                Method::POST,
+                &format!("/auth/tokens/{token_id}/policies/rrsets/"),
+                Some(fields.body()),
+            )
+            .await
+    }
+
+    /// Replaces all fields of the token policy.
+    pub async fn patch_policy(
+        &self,
+        token_id: &str,
+        policy_id: &str,
+        fields: PolicyFields,
+    ) -> Result<TokenPolicy, Error> {
+        self.client
+            .json(
+                Method::PATCH,
+                &format!("/auth/tokens/{token_id}/policies/rrsets/{policy_id}/"),
+                Some(fields.body()),
+            )
+            .await
+    }
+
+    pub async fn get_policy(&self, token_id: &str, policy_id: &str) -> Result<TokenPolicy, Error> {
+        self.client
+            .json(
+                Method::GET,
+                &format!("/auth/tokens/{token_id}/policies/rrsets/{policy_id}/"),
+                None,
+            )
+            .await
+    }
+
+    pub async fn list_policies(&self, token_id: &str) -> Result<Vec<TokenPolicy>, Error> {
+        self.client
+            .json(
+                Method::GET,
+                &format!("/auth/tokens/{token_id}/policies/rrsets/"),
+                None,
+            )
+            .await
+    }
+
+    pub async fn delete_policy(&self, token_id: &str, policy_id: &str) -> Result<(), Error> {
+        self.client
+            .send(
+                Method::DELETE,
+                &format!("/auth/tokens/{token_id}/policies/rrsets/{policy_id}/"),
+                None,
+            )
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn cleared_fields_sent_as_null() {
+        let fields = TokenFields {
+            name: Some(String::from("ci")),
+            max_age: Patch::Clear,
+            max_unused_period: Patch::Clear,
+            ..TokenFields::default()
+        };
+        assert_eq!(
+            fields.body(),
+            json!({ "name": "ci", "max_age": null, "max_unused_period": null })
+        );
+    }
+
+    #[test]
+    fn unchanged_fields_not_sent() {
+        let fields = TokenFields {
+            max_age: Patch::Set(String::from("1 00:00:00")),
+            ..TokenFields::default()
+        };
+        assert_eq!(fields.body(), json!({ "max_age": "1 00:00:00" }));
+    }
+}
@@ -0,0 +1,246 @@
+//! Recording of all requests to the API and their responses for `--trace-http`,
+//! e.g. to attach them to a support ticket or to replay them against a mock server.
+//!
+//! Every attempt of a request is recorded as an entry of an HTTP Archive (HAR) with the status,
+//! headers and body the API responded with, retries of throttled requests have the number
+//! of the retry in `_retry`. The token in the Authorization header and passwords and tokens
+//! in bodies are redacted, other bodies are recorded as sent and received.
+
+use super::redact;
+use crate::conflict::Timestamp;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::{Request, StatusCode};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+static RECORDER: OnceLock<Mutex<Recorder>> = OnceLock::new();
+
+struct Recorder {
+    output: Box<dyn Write + Send>,
+    /// Entries of a HAR file, which can only be written as a whole by [`finish`]
+    har: Option<Vec<Value>>,
+}
+
+/// Starts recording into the given file, as HAR for a `.har` extension and JSON Lines otherwise.
+/// Without file the entries are written to stderr as JSON Lines.
+pub fn start(path: Option<&Path>) -> io::Result<()> {
+    let (output, har): (Box<dyn Write + Send>, bool) = match path {
+        Some(path) => (
+            Box::new(BufWriter::new(File::create(path)?)),
+            path.extension().is_some_and(|extension| extension == "har"),
+        ),
+        None => (Box::new(io::stderr()), false),
+    };
+    let recorder = Recorder {
+        output,
+        har: har.then(Vec::new),
+    };
+    RECORDER
+        .set(Mutex::new(recorder))
+        .map_err(|_| io::Error::other("Tracing has already been started"))
+}
+
+/// Writes the HAR file and flushes the output, entries of JSON Lines are written right away.
+pub fn finish() -> io::Result<()> {
+    let Some(recorder) = RECORDER.get() else {
+        return Ok(());
+    };
+    let mut recorder = recorder.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some(entries) = recorder.har.take() {
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "desec_cli", "version": env!("CARGO_PKG_VERSION") },
+                "entries": entries,
+            }
+        });
+        serde_json::to_writer_pretty(&mut recorder.output, &har)?;
+        writeln!(recorder.output)?;
+    }
+    recorder.output.flush()
+}
+
+/// Records an attempt of the request, either with its response or the error it failed with.
+pub(super) fn record(
+    request: &Request,
+    started: SystemTime,
+    elapsed: Duration,
+    retry: usize,
+    response: Result<(StatusCode, &HeaderMap, &str), &reqwest::Error>,
+) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+    let entry = entry(request, started, elapsed, retry, response);
+    let mut recorder = recorder.lock().unwrap_or_else(|error| error.into_inner());
+    match &mut recorder.har {
+        Some(entries) => entries.push(entry),
+        None => {
+            // A broken trace must not fail the command, so write errors are ignored
+            let _ = writeln!(recorder.output, "{entry}");
+        }
+    }
+}
+
+// The HAR entry of an attempt of the request.
+fn entry(
+    request: &Request,
+    started: SystemTime,
+    elapsed: Duration,
+    retry: usize,
+    response: Result<(StatusCode, &HeaderMap, &str), &reqwest::Error>,
+) -> Value {
+    let mut entry = json!({
+        "startedDateTime": Timestamp::from(started).to_string(),
+        "time": elapsed.as_secs_f64() * 1000.0,
+        "request": {
+            "method": request.method().as_str(),
+            "url": request.url().as_str(),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": headers(request.headers()),
+            "queryString": request.url().query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect::<Vec<_>>(),
+            "headersSize": -1,
+            "bodySize": request.body().and_then(|body| body.as_bytes()).map_or(0, <[u8]>::len),
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": elapsed.as_secs_f64() * 1000.0, "receive": 0 },
+        "_retry": retry,
+    });
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        entry["request"]["postData"] = json!({
+            "mimeType": "application/json",
+            "text": redact_text(&String::from_utf8_lossy(body)),
+        });
+    }
+    entry["response"] = match response {
+        Ok((status, headers_map, text)) => json!({
+            "status": status.as_u16(),
+            "statusText": status.canonical_reason().unwrap_or_default(),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": headers(headers_map),
+            "content": {
+                "size": text.len(),
+                "mimeType": headers_map
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default(),
+                "text": redact_text(text),
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": text.len(),
+        }),
+        // HAR has no place for failed requests, they get the status 0 browsers use
+        Err(error) => json!({
+            "status": 0,
+            "statusText": "",
+            "httpVersion": "",
+            "cookies": [],
+            "headers": [],
+            "content": { "size": 0, "mimeType": "" },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+            "_error": error.to_string(),
+        }),
+    };
+    entry
+}
+
+fn headers(headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match name == AUTHORIZATION {
+                true => "Token ***",
+                false => value.to_str().unwrap_or_default(),
+            };
+            json!({ "name": name.as_str(), "value": value })
+        })
+        .collect()
+}
+
+// The body with secrets masked, bodies without secrets are kept as they are.
+fn redact_text(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(body) => {
+            let redacted = redact(body.clone());
+            match redacted == body {
+                true => text.to_string(),
+                false => redacted.to_string(),
+            }
+        }
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, RETRY_AFTER};
+
+    fn request() -> Request {
+        let mut authorization = HeaderValue::from_static("Token secret");
+        authorization.set_sensitive(true);
+        reqwest::Client::new()
+            .patch("https://desec.io/api/v1/auth/tokens/1/?x=y")
+            .header(AUTHORIZATION, authorization)
+            .body(r#"{"name":"ci","token":"secret"}"#)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn throttled_attempt() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        let body = r#"{"detail": "Request was throttled."}"#;
+        let entry = entry(
+            &request(),
+            SystemTime::UNIX_EPOCH,
+            Duration::from_millis(5),
+            1,
+            Ok((StatusCode::TOO_MANY_REQUESTS, &headers, body)),
+        );
+        assert_eq!(entry["_retry"], 1);
+        assert_eq!(entry["response"]["status"], 429);
+        assert_eq!(
+            entry["response"]["headers"],
+            json!([{ "name": "retry-after", "value": "3" }])
+        );
+        // Bodies without secrets are recorded as received
+        assert_eq!(entry["response"]["content"]["text"], body);
+    }
+
+    #[test]
+    fn secrets_redacted() {
+        let entry = entry(
+            &request(),
+            SystemTime::UNIX_EPOCH,
+            Duration::from_millis(5),
+            0,
+            Ok((StatusCode::OK, &HeaderMap::new(), r#"{"token":"secret"}"#)),
+        );
+        assert_eq!(
+            entry["request"]["headers"],
+            json!([{ "name": "authorization", "value": "Token ***" }])
+        );
+        assert_eq!(
+            entry["request"]["postData"]["text"],
+            r#"{"name":"ci","token":"***"}"#
+        );
+        assert_eq!(
+            entry["request"]["queryString"],
+            json!([{ "name": "x", "value": "y" }])
+        );
+        assert_eq!(entry["response"]["content"]["text"], r#"{"token":"***"}"#);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{rrset_name, zone};
+use desec_api::rrset::ResourceRecordSet;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The state of a single rrset as sent in bulk requests.
+/// Empty records delete the rrset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RrsetState {
+    pub subname: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub ttl: u64,
+    pub records: Vec<String>,
+}
+
+impl From<&ResourceRecordSet> for RrsetState {
+    fn from(rrset: &ResourceRecordSet) -> Self {
+        RrsetState {
+            subname: rrset.subname.clone().unwrap_or_default(),
+            r#type: rrset.rrset_type.clone(),
+            ttl: rrset.ttl,
+            records: rrset.records.clone(),
+        }
+    }
+}
+
+impl RrsetState {
+    /// The state which deletes the rrset.
+    pub fn deleted(&self) -> Self {
+        RrsetState {
+            records: vec![],
+            ..self.clone()
+        }
+    }
+}
+
+/// A change of a single rrset from its current to its new state.
+#[derive(Clone, Debug)]
+pub struct RrsetChange {
+    pub domain: String,
+    /// None if the rrset does not exist yet
+    pub before: Option<RrsetState>,
+    pub after: RrsetState,
+}
+
+impl RrsetChange {
+    /// The state which reverts the change.
+    pub fn reverted(&self) -> RrsetState {
+        match &self.before {
+            Some(before) => before.clone(),
+            None => self.after.deleted(),
+        }
+    }
+}
+
+impl fmt::Display for RrsetChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subname = rrset_name::display_subname(&self.after.subname);
+        let (marker, ttl) = match (&self.before, self.after.records.is_empty()) {
+            (None, _) => ('+', format!("ttl {}", self.after.ttl)),
+            (Some(before), true) => ('-', format!("ttl {}", before.ttl)),
+            (Some(before), false) if before.ttl != self.after.ttl => {
+                ('~', format!("ttl {} -> {}", before.ttl, self.after.ttl))
+            }
+            (Some(_), false) => ('~', format!("ttl {}", self.after.ttl)),
+        };
+        writeln!(
+            f,
+            "{marker} {} {subname}.{} ({ttl})",
+            self.after.r#type, self.domain
+        )?;
+        let before = self
+            .before
+            .as_ref()
+            .map(|before| before.records.as_slice())
+            .unwrap_or_default();
+        for record in before {
+            match self.after.records.contains(record) {
+                true => writeln!(f, "      {record}")?,
+                false => writeln!(f, "  -   {record}")?,
+            }
+        }
+        for record in &self.after.records {
+            if !before.contains(record) {
+                writeln!(f, "  +   {record}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A file holding the previous state of changed rrsets, which can be applied to revert the changes.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct UndoFile {
+    pub domains: BTreeMap<String, Vec<RrsetState>>,
+}
+
+impl UndoFile {
+    pub fn new(changes: &[RrsetChange]) -> Self {
+        let mut undo = UndoFile::default();
+        for change in changes {
+            undo.domains
+                .entry(change.domain.clone())
+                .or_default()
+                .push(change.reverted());
+        }
+        undo
+    }
+}
+
+/// Groups the new states of the changes by domain, as needed for bulk requests.
+pub fn by_domain(changes: &[RrsetChange]) -> BTreeMap<String, Vec<RrsetState>> {
+    let mut domains: BTreeMap<String, Vec<RrsetState>> = BTreeMap::new();
+    for change in changes {
+        domains
+            .entry(change.domain.clone())
+            .or_default()
+            .push(change.after.clone());
+    }
+    domains
+}
+
+/// Replaces every occurrence of `from` in the record which is not part of a longer name or address,
+/// e.g. 192.0.2.1 is replaced in `ip4:192.0.2.1` but not in `192.0.2.10`
+/// and 2001:db8::1 is replaced in `ip6:2001:db8::1` but not in `2001:db8::1:5`.
+pub fn replace_value(record: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return record.to_string();
+    }
+    let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_');
+    let mut result = String::with_capacity(record.len());
+    let mut copied = 0;
+    for (index, _) in record.match_indices(from) {
+        let end = index + from.len();
+        let mut preceding = record[..index].chars().rev();
+        let starts_word = match preceding.next() {
+            None => true,
+            // A colon continues an IPv6 address, but not a prefix like `ip6:`
+            Some(':') => match preceding.next() {
+                Some(':') => false,
+                Some(c) if c.is_ascii_hexdigit() => !std::iter::once(c)
+                    .chain(preceding.take_while(char::is_ascii_alphanumeric))
+                    .all(|c| c.is_ascii_hexdigit()),
+                _ => true,
+            },
+            Some(c) => !is_word(c),
+        };
+        let mut following = record[end..].chars();
+        let ends_word = match following.next() {
+            None => true,
+            // The trailing dot of a fully qualified name does not continue it
+            Some('.') => following.next().map_or(true, |c| !is_word(c)),
+            Some(':') => following
+                .next()
+                .map_or(true, |c| !(c.is_ascii_hexdigit() || c == ':')),
+            Some(c) => !is_word(c),
+        };
+        if starts_word && ends_word {
+            result.push_str(&record[copied..index]);
+            result.push_str(to);
+            copied = end;
+        }
+    }
+    result.push_str(&record[copied..]);
+    result
+}
+
+/// Computes the change replacing `from` with `to` in the records of the rrset, if any.
+pub fn replace_in(rrset: &ResourceRecordSet, from: &str, to: &str) -> Option<RrsetChange> {
+    let before = RrsetState::from(rrset);
+    let mut records: Vec<String> = vec![];
+    for record in &before.records {
+        let record = replace_value(record, from, to);
+        if !records.contains(&record) {
+            records.push(record);
+        }
+    }
+    if records == before.records {
+        return None;
+    }
+    Some(RrsetChange {
+        domain: rrset.domain.clone(),
+        after: RrsetState {
+            records,
+            ..before.clone()
+        },
+        before: Some(before),
+    })
+}
+
+/// Moves the target name of a CNAME, MX or SRV record from the zone `from` to the zone `to`
+/// if it lies within `from`, e.g. `10 mail.example.com.` becomes `10 mail.example.org.`.
+pub fn move_target(record: &str, from: &str, to: &str) -> String {
+    let (prefix, target) = match record.rsplit_once(' ') {
+        Some((prefix, target)) => (Some(prefix), target),
+        None => (None, record),
+    };
+    // Only fully qualified targets can be attributed to a zone
+    let Some(name) = target.strip_suffix('.') else {
+        return record.to_string();
+    };
+    let lowercase = name.to_ascii_lowercase();
+    let from = from.trim_end_matches('.').to_ascii_lowercase();
+    let to = to.trim_end_matches('.');
+    let moved = if lowercase == from {
+        format!("{to}.")
+    } else if lowercase.ends_with(&format!(".{from}")) {
+        format!("{}{to}.", &name[..name.len() - from.len()])
+    } else {
+        return record.to_string();
+    };
+    match prefix {
+        Some(prefix) => format!("{prefix} {moved}"),
+        None => moved,
+    }
+}
+
+/// Computes the changes copying the rrsets of the zone `from` into the zone `to`,
+/// given the rrsets which already exist there. DNSSEC rrsets are managed by deSEC
+/// for each zone and not copied.
+pub fn copy_zone(
+    rrsets: &[ResourceRecordSet],
+    existing: &[ResourceRecordSet],
+    from: &str,
+    to: &str,
+) -> Vec<RrsetChange> {
+    let mut changes = vec![];
+    for rrset in rrsets
+        .iter()
+        .filter(|rrset| !zone::DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+    {
+        let mut after = RrsetState::from(rrset);
+        if matches!(after.r#type.as_str(), "CNAME" | "MX" | "SRV") {
+            after.records = after
+                .records
+                .iter()
+                .map(|record| move_target(record, from, to))
+                .collect();
+        }
+        let before = existing
+            .iter()
+            .map(RrsetState::from)
+            .find(|state| state.subname == after.subname && state.r#type == after.r#type);
+        if before.as_ref() != Some(&after) {
+            changes.push(RrsetChange {
+                domain: to.to_string(),
+                before,
+                after,
+            });
+        }
+    }
+    changes
+}
+
+/// Computes the changes turning the current rrsets of a domain into the desired ones,
+/// current rrsets missing from the desired ones are deleted.
+pub fn diff(domain: &str, current: &[RrsetState], desired: &[RrsetState]) -> Vec<RrsetChange> {
+    let sorted = |state: &RrsetState| {
+        let mut records = state.records.clone();
+        records.sort();
+        records
+    };
+    let find = |states: &[RrsetState], state: &RrsetState| {
+        states
+            .iter()
+            .find(|other| other.subname == state.subname && other.r#type == state.r#type)
+            .cloned()
+    };
+    let mut changes = vec![];
+    for after in desired {
+        let before = find(current, after);
+        let unchanged = before
+            .as_ref()
+            .is_some_and(|before| before.ttl == after.ttl && sorted(before) == sorted(after));
+        if !unchanged {
+            changes.push(RrsetChange {
+                domain: domain.to_string(),
+                before,
+                after: after.clone(),
+            });
+        }
+    }
+    for before in current {
+        if find(desired, before).is_none() {
+            changes.push(RrsetChange {
+                domain: domain.to_string(),
+                before: Some(before.clone()),
+                after: before.deleted(),
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_zone, replace_value};
+    use desec_api::rrset::ResourceRecordSet;
+
+    fn rrset(subname: &str, r#type: &str, records: &[&str]) -> ResourceRecordSet {
+        ResourceRecordSet {
+            created: String::new(),
+            domain: String::from("example.com"),
+            subname: Some(subname.to_string()),
+            name: String::new(),
+            rrset_type: r#type.to_string(),
+            ttl: 3600,
+            records: records.iter().map(|record| record.to_string()).collect(),
+            touched: String::new(),
+        }
+    }
+
+    #[test]
+    fn replace_value_ipv4() {
+        assert_eq!(
+            replace_value("v=spf1 ip4:192.0.2.1 -all", "192.0.2.1", "192.0.2.2"),
+            "v=spf1 ip4:192.0.2.2 -all"
+        );
+        assert_eq!(
+            replace_value("192.0.2.1", "192.0.2.1", "192.0.2.2"),
+            "192.0.2.2"
+        );
+        assert_eq!(
+            replace_value("192.0.2.10", "192.0.2.1", "192.0.2.2"),
+            "192.0.2.10"
+        );
+        assert_eq!(
+            replace_value("10.192.0.2.1", "192.0.2.1", "192.0.2.2"),
+            "10.192.0.2.1"
+        );
+    }
+
+    #[test]
+    fn replace_value_ipv6() {
+        assert_eq!(
+            replace_value("v=spf1 ip6:2001:db8::1 -all", "2001:db8::1", "2001:db8::2"),
+            "v=spf1 ip6:2001:db8::2 -all"
+        );
+        assert_eq!(
+            replace_value("2001:db8::1", "2001:db8::1", "2001:db8::2"),
+            "2001:db8::2"
+        );
+        assert_eq!(
+            replace_value("2001:db8::1:5", "2001:db8::1", "2001:db8::2"),
+            "2001:db8::1:5"
+        );
+        assert_eq!(
+            replace_value("2001:db8::1", "db8::1", "db8::2"),
+            "2001:db8::1"
+        );
+        assert_eq!(replace_value("2001:db8::1", "::1", "::2"), "2001:db8::1");
+        assert_eq!(
+            replace_value("ip6:2001:db8::/32", "2001:db8::", "2001:db9::"),
+            "ip6:2001:db9::/32"
+        );
+    }
+
+    #[test]
+    fn replace_value_names() {
+        assert_eq!(
+            replace_value("10 mail.example.com.", "mail.example.com", "mx.example.net"),
+            "10 mx.example.net."
+        );
+        assert_eq!(
+            replace_value(
+                "10 mail.example.com.au.",
+                "mail.example.com",
+                "mx.example.net"
+            ),
+            "10 mail.example.com.au."
+        );
+        assert_eq!(
+            replace_value(
+                "10 webmail.example.com.",
+                "mail.example.com",
+                "mx.example.net"
+            ),
+            "10 webmail.example.com."
+        );
+    }
+
+    #[test]
+    fn copy_zone_skips_dnssec() {
+        let rrsets = [
+            rrset("", "DNSKEY", &["257 3 13 abc"]),
+            rrset("", "CDS", &["1 13 2 abc"]),
+            rrset("", "CDNSKEY", &["257 3 13 abc"]),
+            rrset("www", "CNAME", &["example.com."]),
+        ];
+        let changes = copy_zone(&rrsets, &[], "example.com", "example.net");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].domain, "example.net");
+        assert_eq!(changes[0].after.r#type, "CNAME");
+        assert_eq!(changes[0].after.records, ["example.net."]);
+    }
+}
@@ -1,15 +1,24 @@
-use clap::{ArgAction, Args, Parser, Subcommand};
+use crate::complete;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter};
 use clap_complete::{generate, Generator, Shell};
+use desec_cli::api::Patch;
+use desec_cli::command::token::TokenPatch;
+use desec_cli::conflict::Timestamp;
 use std::io;
+use std::path::PathBuf;
 
 // Top level clap::Command
 #[derive(Parser)]
 #[clap(name = "mycli")]
 #[command(name = "desec_cli")]
 pub struct Cli {
-    /// Error messages are suppressed
+    /// Error and status messages are suppressed, only the output of the command is printed
     #[clap(long, short, global = true, default_value_t = false)]
     pub quiet: bool,
+    /// Show more details on stderr, -v the requests to the API, -vv their bodies, -vvv also of libraries
+    #[clap(long, short, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
     /// Whether to disable retry of throttled requests which would incure sleeps
     #[clap(long, global = true, default_value_t = false)]
     pub no_retry: bool,
@@ -19,7 +28,35 @@ pub struct Cli {
     /// Maximum number of retries per request
     #[clap(long, global = true, required = false)]
     pub max_retries: Option<usize>,
-    /// Generates completion for given shell
+    /// Skip confirmation prompts of destructive commands
+    #[clap(long, short, global = true, default_value_t = false)]
+    pub yes: bool,
+    /// Print the requests of mutating commands and their effect instead of sending them
+    #[clap(long, global = true, default_value_t = false)]
+    pub dry_run: bool,
+    /// Abort writes to domains changed after this time, e.g. the touched value of `domain get`
+    #[clap(long, global = true, value_name = "TIMESTAMP")]
+    pub if_unchanged_since: Option<Timestamp>,
+    /// Record all requests and responses with redacted token into FILE,
+    /// as HAR for a .har file and JSON Lines otherwise, to stderr without FILE
+    #[clap(
+        long,
+        global = true,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "-"
+    )]
+    pub trace_http: Option<PathBuf>,
+    /// Output format of list commands, jsonl prints one object per line,
+    /// zone prints the rrsets of `rrset list` as zone file
+    #[clap(long, global = true, value_enum, default_value_t = OutputFormat::Json)]
+    pub output: OutputFormat,
+    /// Comma separated fields to keep in the output of list commands, e.g. subname,type,records
+    #[clap(long, global = true, value_delimiter = ',')]
+    pub fields: Vec<String>,
+    /// Generates static completion for given shell.
+    /// For completion of domains, subnames, types, tokens and policies source the output of `COMPLETE=<shell> desec_cli` instead.
     #[arg(long = "generate", value_enum)]
     pub generator: Option<Shell>,
     // Subcommands
@@ -30,6 +67,13 @@ pub struct Cli {
     // Add global-level flags here
 }
 
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Jsonl,
+    Zone,
+}
+
 pub fn print_completions<G: Generator>(gen: G, cmd: &mut clap::Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
@@ -49,6 +93,18 @@ pub enum Command {
     /// Manage Resource Record Sets
     #[clap(name = "rrset")]
     ResourceRecordSet(ResourceRecordSet),
+    /// Manage SPF, DKIM, DMARC and MTA-STS records
+    #[clap(name = "email")]
+    Email(Email),
+    /// Keep the zones of domains as files in a directory, e.g. a git repository
+    #[clap(name = "repo")]
+    Repo(Repo),
+    /// Manage PTR records in the reverse zones of the account
+    #[clap(name = "ptr")]
+    Ptr(Ptr),
+    /// Browse and edit the rrsets of all domains interactively
+    #[clap(name = "tui")]
+    Tui,
     /// Manage Token
     #[clap(name = "token")]
     Token(Token),
@@ -81,6 +137,24 @@ pub struct ResourceRecordSet {
     pub command: ResourceRecordSetCommand,
 }
 
+#[derive(Parser)]
+pub struct Email {
+    #[structopt(subcommand)]
+    pub command: EmailCommand,
+}
+
+#[derive(Parser)]
+pub struct Repo {
+    #[structopt(subcommand)]
+    pub command: RepoCommand,
+}
+
+#[derive(Parser)]
+pub struct Ptr {
+    #[structopt(subcommand)]
+    pub command: PtrCommand,
+}
+
 #[derive(Parser)]
 pub struct Token {
     #[structopt(subcommand)]
@@ -119,9 +193,21 @@ pub enum DomainCommand {
     Get(DomainNameArg),
     List,
     Create(DomainNameArg),
-    Delete(DomainNameArg),
+    Delete(DomainDeleteArgs),
     Responsible(DomainNameArg),
-    Export(DomainNameArg),
+    Export(DomainExportArgs),
+    /// Edit the rrsets of a domain in $EDITOR and apply the changes after confirmation
+    Edit(DomainEditArgs),
+    /// Create a domain if needed and copy all rrsets of another domain into it
+    Clone(DomainCloneArgs),
+    /// Add the rrsets of a built-in or user template to a domain
+    ApplyTemplate(DomainApplyTemplateArgs),
+    /// List the available templates
+    ListTemplates,
+    /// Check that A, AAAA and PTR records of the account match each other
+    FcrdnsReport(DomainFcrdnsReportArgs),
+    /// Check the rrsets of a domain or a zone file for common mistakes
+    Lint(DomainLintArgs),
 }
 
 // The command enum for the 'rrset' command
@@ -131,6 +217,49 @@ pub enum ResourceRecordSetCommand {
     List(ResourceRecordSetListArgs),
     Create(ResourceRecordSetCreateArgs),
     Delete(ResourceRecordSetDeleteArgs),
+    /// Search rrsets across all domains of the account
+    Search(ResourceRecordSetSearchArgs),
+    /// Replace a value in the records of all domains, e.g. an IP address or a target name
+    Replace(ResourceRecordSetReplaceArgs),
+    /// Restore the rrsets saved in an undo file written by replace
+    Restore(ResourceRecordSetRestoreArgs),
+    /// Change the records of an rrset after lowering its TTL and restore the TTL afterwards
+    Migrate(ResourceRecordSetMigrateArgs),
+}
+
+// The command enum for the 'email' command
+#[derive(Subcommand, Clone)]
+pub enum EmailCommand {
+    /// Show and check the current mail records of a domain
+    Report(EmailReportArgs),
+    /// Set the SPF policy listing the servers allowed to send mail
+    Spf(EmailSpfArgs),
+    /// Publish a DKIM public key
+    Dkim(EmailDkimArgs),
+    /// Set the DMARC policy
+    Dmarc(EmailDmarcArgs),
+    /// Set the MTA-STS and TLS-RPT records
+    MtaSts(EmailMtaStsArgs),
+}
+
+// The command enum for the 'repo' command
+#[derive(Subcommand, Clone)]
+pub enum RepoCommand {
+    /// Set up a directory for the zone files
+    Init(RepoInitArgs),
+    /// Write the zone files of all domains from their current rrsets
+    Pull(RepoDirArgs),
+    /// Apply the changes of the zone files, refused for domains changed since the last pull
+    Push(RepoDirArgs),
+}
+
+// The command enum for the 'ptr' command
+#[derive(Subcommand, Clone)]
+pub enum PtrCommand {
+    /// Point the reverse name of an address to a hostname
+    Set(PtrSetArgs),
+    /// Point the addresses of all A and AAAA records of a zone back to their names
+    Sync(PtrSyncArgs),
 }
 
 // The command enum for the 'token' command
@@ -157,7 +286,300 @@ pub enum TokenPolicyCommand {
 #[derive(Args, Clone)]
 pub struct DomainNameArg {
     /// The name off the domain to get
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+}
+
+// The final clap::Args struct for the domain export command
+#[derive(Args, Clone)]
+pub struct DomainExportArgs {
+    /// The name off the domain to export
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// Render the zone file from the rrsets with stable sorting and alignment
+    /// instead of printing the zone file of the server
+    #[clap(long)]
+    pub local: bool,
+    #[command(flatten)]
+    pub zone: ZoneArgs,
+}
+
+// The final clap::Args struct for the domain edit command
+#[derive(Args, Clone)]
+pub struct DomainEditArgs {
+    /// The name of the domain to edit
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// Format of the file opened in the editor
+    #[clap(long, value_enum, default_value_t = EditFormat::Zone)]
+    pub format: EditFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum EditFormat {
+    Zone,
+    Yaml,
+}
+
+#[derive(Args, Clone)]
+pub struct DomainFcrdnsReportArgs {
+    /// Maximum number of domains fetched concurrently
+    #[clap(long, default_value_t = 4)]
+    pub parallel: usize,
+}
+
+// The final clap::Args struct for the domain lint command
+#[derive(Args, Clone)]
+pub struct DomainLintArgs {
+    /// The name of the domain or the path of a zone file
+    #[arg(value_name = "NAME|ZONEFILE", add = ArgValueCandidates::new(complete::domains))]
+    pub zone: String,
+    /// The domain of the zone file, by default its file name without the .zone extension
+    #[clap(long)]
+    pub domain: Option<String>,
+    /// The minimum TTL of the domain the TTLs of the zone file are checked against
+    #[clap(long, default_value_t = 3600)]
+    pub minimum_ttl: u64,
+}
+
+impl DomainLintArgs {
+    /// Whether a local zone file is checked instead of the rrsets of the API.
+    pub fn is_file(&self) -> bool {
+        std::path::Path::new(&self.zone).is_file()
+    }
+}
+
+// The final clap::Args struct for the domain delete command
+#[derive(Args, Clone)]
+pub struct DomainDeleteArgs {
+    /// The name off the domain to delete
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// Allow deleting a domain listed in protected_domains of the config file
+    #[clap(long)]
+    pub force_protected: bool,
+}
+
+// The final clap::Args struct for the domain clone command
+#[derive(Args, Clone)]
+pub struct DomainCloneArgs {
+    /// The domain to copy the rrsets from
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub source: String,
+    /// The domain to copy the rrsets to, created if it does not exist
+    pub target: String,
+    /// Profile of the config file holding the source domain, defaults to the env vars
+    #[clap(long)]
+    pub from_profile: Option<String>,
+    /// Profile of the config file to create the target domain in, defaults to the env vars
+    #[clap(long)]
+    pub to_profile: Option<String>,
+    /// Type of rrsets not to copy, can be given multiple times
+    #[clap(long, add = ArgValueCandidates::new(complete::record_types))]
+    pub exclude_type: Vec<String>,
+}
+
+// The final clap::Args struct for the domain apply-template command
+#[derive(Args, Clone)]
+pub struct DomainApplyTemplateArgs {
+    /// The domain to apply the template to
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// The name of the template, user templates are read from templates/<name>.yaml in the config directory
+    #[arg(add = ArgValueCandidates::new(complete::templates))]
+    pub template: String,
+    /// Value of a template variable, can be given multiple times
+    #[clap(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub vars: Vec<(String, String)>,
+}
+
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err(String::from("expected KEY=VALUE")),
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct EmailReportArgs {
+    /// The domain name
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// The subname receiving or sending the mail, @ for the zone apex
+    #[clap(long, default_value = "@")]
+    pub subname: String,
+    /// DNS over HTTPS resolver for names outside of deSEC, e.g. https://dns.google/resolve
+    #[clap(long, value_name = "URL")]
+    pub resolver: Option<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct EmailSpfArgs {
+    /// The domain name
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// The subname sending the mail, @ for the zone apex
+    #[clap(long, default_value = "@")]
+    pub subname: String,
+    /// Allow the MX hosts of the domain to send mail
+    #[clap(long)]
+    pub mx: bool,
+    /// Allow the A and AAAA addresses of the domain to send mail
+    #[clap(long)]
+    pub a: bool,
+    /// IPv4 address or network allowed to send mail, can be given multiple times
+    #[clap(long)]
+    pub ip4: Vec<String>,
+    /// IPv6 address or network allowed to send mail, can be given multiple times
+    #[clap(long)]
+    pub ip6: Vec<String>,
+    /// Domain whose SPF policy is included, e.g. _spf.google.com, can be given multiple times
+    #[clap(long)]
+    pub include: Vec<String>,
+    /// How receivers treat mail from other servers
+    #[clap(long, value_enum, default_value_t = SpfAll::Softfail)]
+    pub all: SpfAll,
+    /// TTL of the record
+    #[clap(long, default_value_t = 3600)]
+    pub ttl: u64,
+    /// Set the policy even though it exceeds the DNS lookup limit
+    #[clap(long)]
+    pub force: bool,
+    /// DNS over HTTPS resolver for names outside of deSEC, e.g. https://dns.google/resolve
+    #[clap(long, value_name = "URL")]
+    pub resolver: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum SpfAll {
+    /// -all, reject the mail
+    Fail,
+    /// ~all, accept but mark the mail
+    Softfail,
+    /// ?all, no statement
+    Neutral,
+}
+
+#[derive(Args, Clone)]
+pub struct EmailDkimArgs {
+    /// The domain name
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// The subname sending the mail, @ for the zone apex
+    #[clap(long, default_value = "@")]
+    pub subname: String,
+    /// The selector of the key, as configured in the mail server
+    #[clap(long)]
+    pub selector: String,
+    /// PEM file holding the RSA or Ed25519 public key
+    #[clap(long)]
+    pub key_file: PathBuf,
+    /// TTL of the record
+    #[clap(long, default_value_t = 3600)]
+    pub ttl: u64,
+}
+
+#[derive(Args, Clone)]
+pub struct EmailDmarcArgs {
+    /// The domain name
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
+    pub name: String,
+    /// The subname sending the mail, @ for the zone apex
+    #[clap(long, default_value = "@")]
+    pub subname: String,
+    /// How receivers treat mail failing the checks
+    #[clap(long, value_enum)]
+    pub policy: DmarcPolicy,
+    /// Policy for subdomains, defaults to the policy
+    #[clap(long, value_enum)]
+    pub subdomain_policy: Option<DmarcPolicy>,
+    /// Percentage of failing mail the policy is applied to
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub pct: Option<u8>,
+    /// Address receiving aggregate reports, can be given multiple times
+    #[clap(long)]
+    pub rua: Vec<String>,
+    /// Address receiving failure reports, can be given multiple times
+    #[clap(long)]
+    pub ruf: Vec<String>,
+    /// DKIM alignment mode
+    #[clap(long, value_enum)]
+    pub adkim: Option<DmarcAlignment>,
+    /// SPF alignment mode
+    #[clap(long, value_enum)]
+    pub aspf: Option<DmarcAlignment>,
+    /// TTL of the record
+    #[clap(long, default_value_t = 3600)]
+    pub ttl: u64,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum DmarcPolicy {
+    None,
+    Quarantine,
+    Reject,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum DmarcAlignment {
+    Relaxed,
+    Strict,
+}
+
+#[derive(Args, Clone)]
+pub struct EmailMtaStsArgs {
+    /// The domain name
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
     pub name: String,
+    /// The subname receiving the mail, @ for the zone apex
+    #[clap(long, default_value = "@")]
+    pub subname: String,
+    /// Id of the policy, has to change whenever the policy file changes, defaults to the current time
+    #[clap(long)]
+    pub id: Option<String>,
+    /// Address receiving TLS reports, can be given multiple times
+    #[clap(long)]
+    pub rua: Vec<String>,
+    /// Host serving the policy file, mta-sts.<domain> is pointed to it with a CNAME
+    #[clap(long)]
+    pub policy_host: Option<String>,
+    /// TTL of the records
+    #[clap(long, default_value_t = 3600)]
+    pub ttl: u64,
+}
+
+#[derive(Args, Clone)]
+pub struct RepoInitArgs {
+    /// The directory of the repository, created if needed
+    pub dir: PathBuf,
+    /// Glob pattern of the domains to keep in the repository, can be given multiple times,
+    /// all domains by default
+    #[clap(long)]
+    pub domain: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct RepoDirArgs {
+    /// The directory of the repository
+    pub dir: PathBuf,
+}
+
+#[derive(Args, Clone)]
+pub struct PtrSetArgs {
+    /// The IPv4 or IPv6 address
+    pub ip: std::net::IpAddr,
+    /// The hostname the address points to
+    pub hostname: String,
+    /// TTL of the PTR record, by default the one of an existing record or 3600 unless the zone requires a higher one
+    #[clap(long)]
+    pub ttl: Option<u64>,
+}
+
+#[derive(Args, Clone)]
+pub struct PtrSyncArgs {
+    /// The domain whose A and AAAA records get PTR records
+    #[clap(long, add = ArgValueCandidates::new(complete::domains))]
+    pub from_zone: String,
 }
 
 // The final clap::Args struct for the account register command
@@ -226,59 +648,160 @@ pub struct UpdateOutreachPreferenceArgs {
 
 #[derive(Args, Clone)]
 pub struct ResourceRecordSetGetArgs {
-    /// The domain name
-    #[clap(index = 1)]
-    pub name: String,
-    /// The subname for the rrset
-    #[clap(index = 2)]
-    pub subname: String,
-    /// The type of rrset
-    #[clap(index = 3)]
-    pub r#type: String,
+    /// The domain name, subname (@ for the zone apex) and type of the rrset,
+    /// or its fully qualified name and type, e.g. www.example.com A
+    #[clap(
+        num_args = 2..=3,
+        required = true,
+        value_names = ["NAME", "SUBNAME", "TYPE"],
+        add = ArgValueCompleter::new(complete::RrsetNameCompleter::existing())
+    )]
+    pub rrset: Vec<String>,
 }
 
 #[derive(Args, Clone)]
 pub struct ResourceRecordSetListArgs {
     /// The domain name
-    #[clap(index = 1)]
+    #[clap(index = 1, add = ArgValueCandidates::new(complete::domains))]
     pub name: String,
+    /// Only list rrsets with this subname, @ for the zone apex
+    #[clap(long, add = ArgValueCandidates::new(complete::subnames))]
+    pub subname: Option<String>,
+    /// Only list rrsets of this type
+    #[clap(long = "type", add = ArgValueCandidates::new(complete::record_types))]
+    pub r#type: Option<String>,
+    /// Regular expression which at least one record of an rrset has to match
+    #[clap(long = "match")]
+    pub pattern: Option<String>,
+    /// Sort the rrsets by the given field
+    #[clap(long, value_enum)]
+    pub sort: Option<RrsetSortKey>,
+    #[command(flatten)]
+    pub zone: ZoneArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct ZoneArgs {
+    /// Use fully qualified names in zone files instead of names relative to $ORIGIN
+    #[clap(long)]
+    pub absolute: bool,
+    /// Include the DNSKEY, CDNSKEY and CDS records in zone files
+    #[clap(long)]
+    pub dnssec: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum RrsetSortKey {
+    Subname,
+    Type,
+    Ttl,
 }
 
 #[derive(Args, Clone)]
 pub struct ResourceRecordSetCreateArgs {
-    /// The domain name
-    #[clap(index = 1)]
-    pub name: String,
-    /// The subname for the rrset
-    #[clap(index = 2)]
-    pub subname: String,
-    /// The type of rrset
-    #[clap(index = 3)]
-    pub r#type: String,
-    /// TTL of the rrset
-    #[clap(index = 4)]
-    pub ttl: u64,
-    /// TTL of the rrset
-    #[clap(index = 5)]
-    pub records: Vec<String>,
+    /// The domain name, subname (@ for the zone apex), type, TTL and records of the rrset,
+    /// the domain name and subname can be replaced by the fully qualified name,
+    /// e.g. www.example.com A 3600 192.0.2.1
+    #[clap(
+        num_args = 3..,
+        required = true,
+        value_names = ["NAME", "SUBNAME", "TYPE", "TTL", "RECORDS"],
+        add = ArgValueCompleter::new(complete::RrsetNameCompleter::new())
+    )]
+    pub rrset: Vec<String>,
 }
 
 #[derive(Args, Clone)]
 pub struct ResourceRecordSetDeleteArgs {
-    /// The domain name
-    #[clap(index = 1)]
-    pub name: String,
-    /// The subname for the rrset
-    #[clap(index = 2)]
-    pub subname: String,
-    /// The type of rrset
-    #[clap(index = 3)]
-    pub r#type: String,
+    /// The domain name, subname (@ for the zone apex) and type of the rrset,
+    /// or its fully qualified name and type, e.g. www.example.com A
+    #[clap(
+        num_args = 2..=3,
+        required = true,
+        value_names = ["NAME", "SUBNAME", "TYPE"],
+        add = ArgValueCompleter::new(complete::RrsetNameCompleter::existing())
+    )]
+    pub rrset: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct ResourceRecordSetSearchArgs {
+    /// Regular expression matched against each record of an rrset
+    #[clap(long)]
+    pub value: Option<String>,
+    /// Type of the rrsets to search for, can be given multiple times
+    #[clap(long = "type", add = ArgValueCandidates::new(complete::record_types))]
+    pub types: Vec<String>,
+    /// Glob pattern for the subname, @ stands for the zone apex
+    #[clap(long)]
+    pub subname: Option<String>,
+    /// Glob pattern for the domains to search in
+    #[clap(long)]
+    pub domain: Option<String>,
+    /// Minimum TTL of the rrsets
+    #[clap(long)]
+    pub min_ttl: Option<u64>,
+    /// Maximum TTL of the rrsets
+    #[clap(long)]
+    pub max_ttl: Option<u64>,
+    /// Maximum number of domains fetched concurrently
+    #[clap(long, default_value_t = 4)]
+    pub parallel: usize,
+}
+
+#[derive(Args, Clone)]
+pub struct ResourceRecordSetReplaceArgs {
+    /// Value to replace, only occurrences which are not part of a longer name or address are replaced
+    #[clap(long)]
+    pub from: String,
+    /// Value to replace it with
+    #[clap(long)]
+    pub to: String,
+    /// Type of the rrsets to change, can be given multiple times
+    #[clap(long = "type", add = ArgValueCandidates::new(complete::record_types))]
+    pub types: Vec<String>,
+    /// Glob pattern for the domains to change
+    #[clap(long)]
+    pub domain: Option<String>,
+    /// File to save the previous state of the changed rrsets to.
+    /// Defaults to desec-undo-<timestamp>.json in the current directory.
+    #[clap(long)]
+    pub undo_file: Option<PathBuf>,
+    /// Maximum number of domains fetched concurrently
+    #[clap(long, default_value_t = 4)]
+    pub parallel: usize,
+}
+
+#[derive(Args, Clone)]
+pub struct ResourceRecordSetRestoreArgs {
+    /// Undo file written by rrset replace
+    pub file: PathBuf,
+}
+
+#[derive(Args, Clone)]
+pub struct ResourceRecordSetMigrateArgs {
+    /// The domain name, subname (@ for the zone apex) and type of the rrset,
+    /// or its fully qualified name and type, e.g. www.example.com A
+    #[clap(
+        num_args = 2..=3,
+        required = true,
+        value_names = ["NAME", "SUBNAME", "TYPE"],
+        add = ArgValueCompleter::new(complete::RrsetNameCompleter::existing())
+    )]
+    pub rrset: Vec<String>,
+    /// The new records, can be given multiple times
+    #[clap(long, required = true, value_name = "RECORD")]
+    pub to: Vec<String>,
+    /// File keeping the progress for resuming the migration,
+    /// desec-migrate-<domain>-<subname>-<type>.json in the current directory by default
+    #[clap(long)]
+    pub state_file: Option<PathBuf>,
 }
 
 #[derive(Args, Clone)]
 pub struct TokenIdArgs {
     /// The token id
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
 }
 
@@ -305,7 +828,7 @@ pub struct TokenCreateArgs {
 #[derive(Args, Clone)]
 pub struct TokenPatchArgs {
     /// Id of the token to patch
-    #[clap(long)]
+    #[clap(long, add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
     /// Name for the token
     #[clap(long)]
@@ -314,48 +837,81 @@ pub struct TokenPatchArgs {
     #[clap(long)]
     #[arg(num_args(0..))]
     pub subnets: Option<Vec<String>>,
+    /// Reset the allowed subnets to the default, which allows all addresses
+    #[clap(long, conflicts_with = "subnets")]
+    pub clear_subnets: bool,
     /// Can manage tokens
     #[clap(long)]
     pub manage: Option<bool>,
-    /// Maximum age for the new token
+    /// Maximum age for the token
     #[clap(long)]
     pub max_age: Option<String>,
+    /// Remove the maximum age of the token
+    #[clap(long, conflicts_with = "max_age")]
+    pub clear_max_age: bool,
     /// Maximum unused period before automatic invalidation
     #[clap(long)]
     pub max_unused_period: Option<String>,
+    /// Remove the maximum unused period of the token
+    #[clap(long, conflicts_with = "max_unused_period")]
+    pub clear_max_unused_period: bool,
+}
+
+impl TokenPatchArgs {
+    /// The changes of the token given by the arguments.
+    pub fn patch(&self) -> TokenPatch {
+        TokenPatch {
+            name: self.name.clone(),
+            allowed_subnets: Patch::new(self.subnets.clone(), self.clear_subnets),
+            perm_manage_tokens: self.manage,
+            max_age: Patch::new(self.max_age.clone(), self.clear_max_age),
+            max_unused_period: Patch::new(
+                self.max_unused_period.clone(),
+                self.clear_max_unused_period,
+            ),
+        }
+    }
 }
 
 #[derive(Args, Clone)]
 pub struct TokenPolicyListArgs {
     /// Id of the token to create a policy for
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
 }
 
 #[derive(Args, Clone)]
 pub struct TokenPolicyGetArgs {
     /// Id of the token to get a policy for
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
     /// Id of the policy to get. If missing, the default policy is returned.
+    #[arg(add = ArgValueCandidates::new(complete::policies))]
     pub policy_id: String,
 }
 
 #[derive(Args, Clone)]
 pub struct TokenPolicyDeleteArgs {
     /// Id of the token to create a policy for
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
     /// Id of the policy to modify
+    #[arg(add = ArgValueCandidates::new(complete::policies))]
     pub policy_id: String,
 }
 
 #[derive(Args, Clone)]
 pub struct TokenPolicyCreateArgs {
     /// Id of the token to create a policy for
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
     /// Domain name to which the policy applies. None for the default policy.
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
     pub domain: Option<String>,
     /// Subname to which the policy applies. None for the default policy.
     pub subname: Option<String>,
     /// Record type to which the policy applies. None for the default policy.
+    #[arg(add = ArgValueCandidates::new(complete::record_types))]
     pub r#type: Option<String>,
     /// Indicates write permission for the RRset specified by (domain, subname, type)
     /// when using the general RRset management or dynDNS interface. Defaults to false.
@@ -365,16 +921,30 @@ pub struct TokenPolicyCreateArgs {
 #[derive(Args, Clone)]
 pub struct TokenPolicyPatchArgs {
     /// Id of the token to modify a policy for
+    #[arg(add = ArgValueCandidates::new(complete::tokens))]
     pub token_id: String,
     /// Id of the token policy to modify
+    #[arg(add = ArgValueCandidates::new(complete::policies))]
     pub policy_id: String,
-    /// Domain name to which the policy applies. Empty string for the default policy.
+    /// Domain name to which the policy applies. Unchanged if missing.
+    #[arg(add = ArgValueCandidates::new(complete::domains))]
     pub domain: Option<String>,
-    /// Subname to which the policy applies. Empty string for the default policy.
+    /// Subname to which the policy applies. Unchanged if missing.
     pub subname: Option<String>,
-    /// Record type to which the policy applies. Empty string for the default policy.
+    /// Record type to which the policy applies. Unchanged if missing.
+    #[arg(add = ArgValueCandidates::new(complete::record_types))]
     pub r#type: Option<String>,
     /// Indicates write permission for the RRset specified by (domain, subname, type)
-    /// when using the general RRset management or dynDNS interface. Defaults to false.
+    /// when using the general RRset management or dynDNS interface. Unchanged if missing.
+    #[arg(action = ArgAction::Set)]
     pub perm_write: Option<bool>,
+    /// Reset the domain to null, so the policy applies to all domains
+    #[clap(long, conflicts_with = "domain")]
+    pub clear_domain: bool,
+    /// Reset the subname to null, so the policy applies to all subnames
+    #[clap(long, conflicts_with = "subname")]
+    pub clear_subname: bool,
+    /// Reset the record type to null, so the policy applies to all types
+    #[clap(long, conflicts_with = "type")]
+    pub clear_type: bool,
 }
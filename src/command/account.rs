@@ -0,0 +1,74 @@
+//! Commands managing the account, most of them work without token.
+
+use super::{Error, Result};
+use crate::api::{account, Client};
+use desec_api::account::{AccountInformation, Captcha, Login, RegisterResponse};
+
+/// Requests a captcha to solve for the registration or a password reset.
+pub async fn captcha() -> Result<Captcha> {
+    account::get_captcha()
+        .await
+        .map_err(Error::api("Failed to get a captcha"))
+}
+
+/// Registers an account, optionally together with its first domain.
+pub async fn register(
+    email: &str,
+    password: &str,
+    captcha_id: &str,
+    captcha_solution: &str,
+    domain: Option<&str>,
+) -> Result<RegisterResponse> {
+    account::register(email, password, captcha_id, captcha_solution, domain)
+        .await
+        .map_err(Error::api(format!(
+            "Registration of account {email} failed"
+        )))
+}
+
+/// Creates a login token for the account.
+pub async fn login(email: &str, password: &str) -> Result<Login> {
+    account::login(email, password)
+        .await
+        .map_err(Error::api(format!("Login of account {email} failed")))
+}
+
+/// Sends a password reset email, if the account exists.
+pub async fn request_password_reset(
+    email: &str,
+    captcha_id: &str,
+    captcha_solution: &str,
+) -> Result<()> {
+    account::request_password_reset(email, captcha_id, captcha_solution)
+        .await
+        .map_err(Error::api(format!(
+            "Requesting a password reset for {email} failed"
+        )))
+}
+
+/// Sets the password with the code of a password reset email.
+pub async fn confirm_password_reset(password: &str, code: &str) -> Result<AccountInformation> {
+    account::confirm_password_reset(password, code)
+        .await
+        .map_err(Error::api("Confirming the password reset failed"))
+}
+
+/// Returns the information of the account the client is authenticated for.
+pub async fn info(client: &Client) -> Result<AccountInformation> {
+    client
+        .account()
+        .get_account_info()
+        .await
+        .map_err(Error::api("Failed to get the account information"))
+}
+
+pub async fn update_outreach_preference(
+    client: &Client,
+    outreach_preference: bool,
+) -> Result<AccountInformation> {
+    client
+        .account()
+        .update_outreach_preference(outreach_preference)
+        .await
+        .map_err(Error::api("Failed to update the outreach preference"))
+}
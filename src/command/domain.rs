@@ -0,0 +1,273 @@
+//! Commands managing domains and their zones.
+
+use super::{Error, Result};
+use crate::api::Client;
+use crate::changeset::{self, RrsetChange, RrsetState};
+use crate::conflict::{self, Timestamp};
+use crate::edit::{self, Edited, Format};
+use crate::fcrdns::{self, Report};
+use crate::lint::{self, Finding};
+use crate::search::{self, RrsetFilter};
+use crate::template::{self, TemplateRrset};
+use crate::zone::{self, ZoneOptions};
+use desec_api::domain::Domain;
+use desec_api::rrset::ResourceRecordSet;
+use glob::Pattern;
+use std::path::Path;
+
+// Maps the not found error of a request for the domain.
+fn not_found(domain: &str, request: String) -> impl FnOnce(desec_api::Error) -> Error + '_ {
+    move |error| match error {
+        desec_api::Error::NotFound => Error::NotFound(format!("Domain {domain}")),
+        error => Error::Api(request, error),
+    }
+}
+
+/// Returns all domains of the account.
+pub async fn list(client: &Client) -> Result<Vec<Domain>> {
+    client
+        .domain()
+        .get_domains()
+        .await
+        .map_err(Error::api("Failed to list domains"))
+}
+
+/// Returns the sorted names of the domains of the account matching the optional glob pattern.
+pub async fn names(client: &Client, pattern: Option<&str>) -> Result<Vec<String>> {
+    let pattern = pattern
+        .map(Pattern::new)
+        .transpose()
+        .map_err(|error| Error::Invalid(format!("Invalid domain pattern: {error}")))?;
+    search::domain_names(client, pattern.as_ref())
+        .await
+        .map_err(Error::api("Failed to list domains"))
+}
+
+/// Returns a single domain.
+pub async fn get(client: &Client, name: &str) -> Result<Domain> {
+    client
+        .domain()
+        .get_domain(name)
+        .await
+        .map_err(not_found(name, format!("Failed to get domain {name}")))
+}
+
+/// Returns the domain of the account a name belongs to.
+pub async fn responsible(client: &Client, name: &str) -> Result<Vec<Domain>> {
+    client
+        .domain()
+        .get_owning_domain(name)
+        .await
+        .map_err(not_found(
+            name,
+            format!("Failed to get the domain of {name}"),
+        ))
+}
+
+pub async fn create(client: &Client, name: &str) -> Result<Domain> {
+    client
+        .domain()
+        .create_domain(name)
+        .await
+        .map_err(Error::api(format!("Creation of domain {name} failed")))
+}
+
+pub async fn delete(client: &Client, name: &str) -> Result<()> {
+    client
+        .domain()
+        .delete_domain(name)
+        .await
+        .map(|_| ())
+        .map_err(Error::api(format!("Deletion of domain {name} failed")))
+}
+
+/// Returns the zone file of the domain as rendered by deSEC.
+pub async fn zonefile(client: &Client, name: &str) -> Result<String> {
+    client
+        .domain()
+        .get_zonefile(name)
+        .await
+        .map_err(not_found(name, format!("Failed to export domain {name}")))
+}
+
+/// Renders the rrsets as zone file, including the DNSSEC records of the domain if requested.
+pub async fn zone(
+    client: &Client,
+    domain: &str,
+    rrsets: &[ResourceRecordSet],
+    dnssec: bool,
+    options: ZoneOptions,
+) -> Result<String> {
+    let keys = match dnssec {
+        true => get(client, domain).await?.keys.unwrap_or_default(),
+        false => vec![],
+    };
+    Ok(zone::render(domain, rrsets, &keys, options))
+}
+
+/// Fails if the domain has been changed after the given time.
+pub async fn check_unchanged_since(client: &Client, domain: &str, since: &Timestamp) -> Result<()> {
+    let touched = get(client, domain).await?.touched;
+    conflict::check_touched(domain, &touched, since)
+}
+
+/// The changes copying the rrsets of one domain into another.
+pub struct ClonePlan {
+    /// Whether the target domain exists, otherwise it has to be created first
+    pub exists: bool,
+    pub changes: Vec<RrsetChange>,
+}
+
+/// Computes the changes copying the rrsets of the source domain into the target domain,
+/// which may belong to another account.
+pub async fn plan_clone(
+    source_client: &Client,
+    target_client: &Client,
+    source: &str,
+    target: &str,
+    exclude_types: &[String],
+) -> Result<ClonePlan> {
+    let mut rrsets = super::rrsets(source_client, source).await?;
+    rrsets.retain(|rrset| {
+        !exclude_types
+            .iter()
+            .any(|r#type| r#type.eq_ignore_ascii_case(&rrset.rrset_type))
+    });
+    let (exists, existing) = match super::rrsets(target_client, target).await {
+        Ok(existing) => (true, existing),
+        Err(Error::NotFound(_)) => (false, vec![]),
+        Err(error) => return Err(error),
+    };
+    Ok(ClonePlan {
+        exists,
+        changes: changeset::copy_zone(&rrsets, &existing, source, target),
+    })
+}
+
+/// Creates the target domain of the clone if it does not exist and writes the changes,
+/// see [`super::rrset::sync`].
+pub async fn clone(
+    client: &Client,
+    target: &str,
+    plan: &ClonePlan,
+    since: Option<&Timestamp>,
+) -> Result<Vec<(String, Result<usize>)>> {
+    if !plan.exists {
+        create(client, target).await?;
+    }
+    super::rrset::sync(client, &plan.changes, since).await
+}
+
+/// Computes the changes applying the template with the given variables to the domain.
+pub async fn plan_template(
+    client: &Client,
+    domain: &str,
+    name: &str,
+    vars: &[(String, String)],
+) -> Result<Vec<RrsetChange>> {
+    let rrsets = template::load(name)
+        .and_then(|template| template.render(domain, vars))
+        .map_err(Error::Invalid)?;
+    plan_merge(client, domain, &rrsets).await
+}
+
+/// Computes the changes merging the rrsets into the existing ones of the domain, like a template.
+pub async fn plan_merge(
+    client: &Client,
+    domain: &str,
+    rrsets: &[TemplateRrset],
+) -> Result<Vec<RrsetChange>> {
+    let existing = super::rrsets(client, domain).await?;
+    Ok(template::merge(domain, rrsets, &existing))
+}
+
+/// Checks the PTR records of the reverse zones against the A and AAAA records of all domains,
+/// fetching at most `parallel` domains at a time.
+pub async fn fcrdns_report(client: &Client, parallel: usize) -> Result<Report> {
+    let domains: Vec<String> = list(client)
+        .await?
+        .into_iter()
+        .map(|domain| domain.name)
+        .collect();
+    let mut rrsets = vec![];
+    for (domain, result) in
+        search::search(client, &domains, &RrsetFilter::default(), parallel).await
+    {
+        match result {
+            Ok(domain_rrsets) => rrsets.push((domain, domain_rrsets)),
+            Err(error) => {
+                return Err(not_found(
+                    &domain,
+                    format!("Failed to get rrsets of domain {domain}"),
+                )(error))
+            }
+        }
+    }
+    Ok(fcrdns::report(&rrsets))
+}
+
+/// Checks the current rrsets of the domain for common mistakes.
+pub async fn lint(client: &Client, name: &str) -> Result<Vec<Finding>> {
+    let domain = get(client, name).await?;
+    let rrsets: Vec<RrsetState> = super::rrsets(client, name)
+        .await?
+        .iter()
+        .filter(|rrset| !zone::DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+        .map(RrsetState::from)
+        .collect();
+    Ok(lint::lint(name, &rrsets, domain.minimum_ttl.into()))
+}
+
+/// Checks a local zone file for common mistakes.
+///
+/// Without domain it is taken from the file name, which must then end with `.zone`.
+pub fn lint_file(path: &Path, domain: Option<&str>, minimum_ttl: u64) -> Result<Vec<Finding>> {
+    let domain = match domain {
+        Some(domain) => domain,
+        None => path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|_| {
+                path.extension()
+                    .is_some_and(|extension| extension == "zone")
+            })
+            .ok_or_else(|| {
+                Error::Invalid(String::from(
+                    "Pass --domain for zone files without .zone extension",
+                ))
+            })?,
+    };
+    let rrsets = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| zone::parse(domain, &text))
+        .map_err(|error| Error::Io(format!("Failed to read {}: {error}", path.display())))?;
+    Ok(lint::lint(domain, &rrsets, minimum_ttl))
+}
+
+/// The result of editing the rrsets of a domain.
+pub enum EditPlan {
+    /// The changes turning the rrsets into the edited ones, empty if nothing has been changed
+    Changes(Vec<RrsetChange>),
+    /// The edit has been cancelled, with the reason
+    Cancelled(&'static str),
+}
+
+/// Opens the rrsets of the domain in `$VISUAL` or `$EDITOR` and computes the changes
+/// to the edited rrsets. The file is reopened with the error until it is valid.
+pub async fn plan_edit(client: &Client, name: &str, format: Format) -> Result<EditPlan> {
+    let rrsets = super::rrsets(client, name).await?;
+    let mut text = edit::render(name, &rrsets, format).map_err(Error::Invalid)?;
+    let path = edit::create_file(name, &text, format).map_err(Error::Io)?;
+    let edited = edit::edit_until_valid(&path, name, &mut text, format);
+    let _ = std::fs::remove_file(&path);
+    let desired = match edited.map_err(Error::Io)? {
+        Edited::Rrsets(desired) => desired,
+        Edited::Cancelled(reason) => return Ok(EditPlan::Cancelled(reason)),
+    };
+    let current: Vec<RrsetState> = rrsets
+        .iter()
+        .filter(|rrset| !zone::DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+        .map(RrsetState::from)
+        .collect();
+    Ok(EditPlan::Changes(changeset::diff(name, &current, &desired)))
+}
@@ -0,0 +1,187 @@
+//! Commands setting and reporting the mail records of domains.
+
+use super::{Error, Result};
+use crate::api::Client;
+use crate::changeset::RrsetChange;
+use crate::config::Config;
+use crate::dns::{self, Resolver};
+use crate::email::{self, Report, SpfCheck};
+use crate::template::TemplateRrset;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The mechanisms of an SPF policy.
+pub struct Spf {
+    pub mx: bool,
+    pub a: bool,
+    pub ip4: Vec<String>,
+    pub ip6: Vec<String>,
+    pub include: Vec<String>,
+    /// The final mechanism, e.g. `~all`
+    pub all: String,
+}
+
+/// The changes setting an SPF policy, with the problems found checking it.
+pub struct SpfPlan {
+    pub check: SpfCheck,
+    pub changes: Vec<RrsetChange>,
+}
+
+/// The tags of a DMARC policy, report addresses may omit `mailto:`.
+pub struct Dmarc {
+    pub policy: String,
+    pub subdomain_policy: Option<String>,
+    pub pct: Option<u8>,
+    pub rua: Vec<String>,
+    pub ruf: Vec<String>,
+    pub adkim: Option<String>,
+    pub aspf: Option<String>,
+}
+
+/// The MTA-STS and TLS-RPT records of a domain.
+pub struct MtaSts {
+    /// The policy id, the current time if not given
+    pub id: Option<String>,
+    /// TLS-RPT report addresses, no TLS-RPT record is set without
+    pub rua: Vec<String>,
+    /// The host serving the policy, which `mta-sts.<domain>` is pointed to
+    pub policy_host: Option<String>,
+}
+
+/// Returns the resolver of the URL, else the one of the config file, else the default one.
+pub fn resolver(url: Option<&str>) -> Result<Resolver> {
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => Config::load()
+            .map_err(Error::Io)?
+            .resolver
+            .unwrap_or_else(|| dns::DEFAULT_RESOLVER.to_string()),
+    };
+    Resolver::new(&url).map_err(Error::Invalid)
+}
+
+/// Reports the mail records at the subname of the domain.
+pub async fn report(
+    client: &Client,
+    resolver: &Resolver,
+    domain: &str,
+    subname: &str,
+) -> Result<Report> {
+    let rrsets = super::rrsets(client, domain).await?;
+    Ok(email::report(resolver, domain, subname, &rrsets).await)
+}
+
+/// Computes the changes setting the SPF policy and checks it, resolving included policies.
+pub async fn plan_spf(
+    client: &Client,
+    resolver: &Resolver,
+    domain: &str,
+    subname: &str,
+    ttl: u64,
+    spf: &Spf,
+) -> Result<SpfPlan> {
+    let policy = email::spf(spf.mx, spf.a, &spf.ip4, &spf.ip6, &spf.include, &spf.all);
+    let check = email::check_spf(resolver, &policy).await;
+    let rrset = txt_rrset(subname.to_string(), ttl, &policy);
+    let changes = super::domain::plan_merge(client, domain, &[rrset]).await?;
+    Ok(SpfPlan { check, changes })
+}
+
+/// Computes the changes publishing the public key of the PEM file for the DKIM selector.
+pub async fn plan_dkim(
+    client: &Client,
+    domain: &str,
+    subname: &str,
+    selector: &str,
+    key_file: &Path,
+    ttl: u64,
+) -> Result<Vec<RrsetChange>> {
+    let pem = std::fs::read_to_string(key_file)
+        .map_err(|error| Error::Io(format!("Failed to read {}: {error}", key_file.display())))?;
+    let key = email::dkim(&pem).map_err(Error::Invalid)?;
+    let domainkey = email::mail_subname("_domainkey", subname);
+    let rrset = txt_rrset(format!("{selector}.{domainkey}"), ttl, &key);
+    super::domain::plan_merge(client, domain, &[rrset]).await
+}
+
+/// Computes the changes setting the DMARC policy.
+pub async fn plan_dmarc(
+    client: &Client,
+    domain: &str,
+    subname: &str,
+    ttl: u64,
+    dmarc: &Dmarc,
+) -> Result<Vec<RrsetChange>> {
+    let addresses = |addresses: &[String]| {
+        Some(
+            addresses
+                .iter()
+                .map(|address| email::mailto(address))
+                .collect::<Vec<_>>(),
+        )
+        .filter(|addresses| !addresses.is_empty())
+        .map(|addresses| addresses.join(","))
+    };
+    let record = email::dmarc(&[
+        ("p", Some(dmarc.policy.clone())),
+        ("sp", dmarc.subdomain_policy.clone()),
+        ("pct", dmarc.pct.map(|pct| pct.to_string())),
+        ("rua", addresses(&dmarc.rua)),
+        ("ruf", addresses(&dmarc.ruf)),
+        ("adkim", dmarc.adkim.clone()),
+        ("aspf", dmarc.aspf.clone()),
+    ]);
+    let rrset = txt_rrset(email::mail_subname("_dmarc", subname), ttl, &record);
+    super::domain::plan_merge(client, domain, &[rrset]).await
+}
+
+/// Computes the changes setting the MTA-STS and TLS-RPT records.
+pub async fn plan_mta_sts(
+    client: &Client,
+    domain: &str,
+    subname: &str,
+    ttl: u64,
+    mta_sts: &MtaSts,
+) -> Result<Vec<RrsetChange>> {
+    let id = mta_sts.id.clone().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+            .to_string()
+    });
+    let mut rrsets = vec![txt_rrset(
+        email::mail_subname("_mta-sts", subname),
+        ttl,
+        &format!("v=STSv1; id={id}"),
+    )];
+    if !mta_sts.rua.is_empty() {
+        let rua: Vec<String> = mta_sts.rua.iter().map(|rua| email::mailto(rua)).collect();
+        rrsets.push(txt_rrset(
+            email::mail_subname("_smtp._tls", subname),
+            ttl,
+            &format!("v=TLSRPTv1; rua={}", rua.join(",")),
+        ));
+    }
+    if let Some(host) = &mta_sts.policy_host {
+        rrsets.push(TemplateRrset {
+            subname: email::mail_subname("mta-sts", subname),
+            r#type: String::from("CNAME"),
+            ttl,
+            replace: true,
+            records: vec![format!("{}.", host.trim_end_matches('.'))],
+        });
+    }
+    super::domain::plan_merge(client, domain, &rrsets).await
+}
+
+// A TXT rrset which adds the content to the existing records.
+fn txt_rrset(subname: String, ttl: u64, content: &str) -> TemplateRrset {
+    TemplateRrset {
+        subname,
+        r#type: String::from("TXT"),
+        ttl,
+        replace: false,
+        records: vec![email::txt_record(content)],
+    }
+}
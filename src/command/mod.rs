@@ -0,0 +1,71 @@
+//! The operations of the CLI, which return their results instead of printing them.
+
+pub mod account;
+pub mod domain;
+pub mod email;
+pub mod ptr;
+pub mod repo;
+pub mod rrset;
+pub mod token;
+
+use crate::api::Client;
+use desec_api::rrset::ResourceRecordSet;
+use std::fmt;
+
+/// Errors of the commands.
+#[derive(Debug)]
+pub enum Error {
+    /// The domain or rrset does not exist or belongs to another account, e.g. `Domain example.com`
+    NotFound(String),
+    /// A request to the API failed, with a description of the request
+    Api(String, desec_api::Error),
+    /// The rrsets have been changed by someone else since they were read
+    Conflict(String),
+    /// The input of the command is not valid, e.g. a malformed zone file
+    Invalid(String),
+    /// Reading or writing a file failed
+    Io(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(what) => write!(f, "{what} does not exist or you are not the owner"),
+            Error::Api(request, error) => write!(f, "{request}: {error}"),
+            Error::Conflict(message) | Error::Invalid(message) | Error::Io(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Api(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Describes a failed API request, e.g. `Error::api("Failed to list domains")`.
+    pub fn api(request: impl Into<String>) -> impl FnOnce(desec_api::Error) -> Error {
+        let request = request.into();
+        move |error| Error::Api(request, error)
+    }
+}
+
+/// Returns all rrsets of the domain.
+pub async fn rrsets(client: &Client, domain: &str) -> Result<Vec<ResourceRecordSet>> {
+    match client.rrset().get_rrsets(domain).await {
+        Ok(rrsets) => Ok(rrsets),
+        Err(desec_api::Error::NotFound) => Err(Error::NotFound(format!("Domain {domain}"))),
+        Err(error) => Err(Error::Api(
+            format!("Failed to get rrsets of domain {domain}"),
+            error,
+        )),
+    }
+}
@@ -0,0 +1,141 @@
+//! Commands maintaining PTR records in the reverse zones of the account.
+
+use super::{Error, Result};
+use crate::api::Client;
+use crate::changeset::{RrsetChange, RrsetState};
+use crate::conflict;
+use crate::reverse;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// The changes adding the PTR records of a zone to the reverse zones.
+pub struct SyncPlan {
+    pub changes: Vec<RrsetChange>,
+    /// Addresses of the zone without a reverse zone in the account
+    pub unhosted: Vec<IpAddr>,
+}
+
+/// Computes the change pointing the reverse name of the address to the hostname,
+/// in the reverse zone of the account which is responsible for it.
+pub async fn plan_set(
+    client: &Client,
+    ip: IpAddr,
+    hostname: &str,
+    ttl: Option<u64>,
+) -> Result<Vec<RrsetChange>> {
+    let name = reverse::reverse_name(ip);
+    let domains = super::domain::responsible(client, &name).await?;
+    let zones: Vec<String> = domains.iter().map(|domain| domain.name.clone()).collect();
+    let Some((zone, subname)) = reverse::split(&name, &zones) else {
+        return Err(Error::NotFound(format!("Reverse zone of {ip}")));
+    };
+    // New records get the usual TTL of an hour, unless the zone requires a higher one
+    let minimum_ttl = domains
+        .iter()
+        .find(|domain| domain.name == zone)
+        .map_or(0, |domain| u64::from(domain.minimum_ttl));
+    let before = super::rrsets(client, zone)
+        .await?
+        .iter()
+        .map(RrsetState::from)
+        .find(|rrset| rrset.subname == subname && rrset.r#type == "PTR");
+    let after = RrsetState {
+        subname,
+        r#type: String::from("PTR"),
+        ttl: ttl
+            .or(before.as_ref().map(|before| before.ttl))
+            .unwrap_or(minimum_ttl.max(3600)),
+        records: vec![reverse::fqdn(hostname)],
+    };
+    Ok(changes(zone, before, after))
+}
+
+/// Computes the changes pointing the addresses of all A and AAAA records of the zone
+/// back to their names, in the reverse zones of the account.
+///
+/// PTR records to names of the zone are replaced, those to other names are kept,
+/// so addresses shared with other zones keep their names.
+pub async fn plan_sync(client: &Client, zone: &str) -> Result<SyncPlan> {
+    let zones: Vec<String> = super::domain::list(client)
+        .await?
+        .into_iter()
+        .map(|domain| domain.name)
+        .filter(|domain| reverse::is_reverse_zone(domain))
+        .collect();
+    let mut plan = SyncPlan {
+        changes: vec![],
+        unhosted: vec![],
+    };
+    // The names of each address by reverse zone and subname, with the TTL of the forward records
+    let mut desired: BTreeMap<(&str, String), (u64, Vec<String>)> = BTreeMap::new();
+    for rrset in super::rrsets(client, zone).await? {
+        if rrset.rrset_type != "A" && rrset.rrset_type != "AAAA" {
+            continue;
+        }
+        let hostname = match rrset.subname.as_deref().unwrap_or_default() {
+            "" => reverse::fqdn(zone),
+            subname => reverse::fqdn(&format!("{subname}.{zone}")),
+        };
+        for record in &rrset.records {
+            let Ok(ip) = record.parse::<IpAddr>() else {
+                continue;
+            };
+            let Some((reverse_zone, subname)) = reverse::split(&reverse::reverse_name(ip), &zones)
+            else {
+                plan.unhosted.push(ip);
+                continue;
+            };
+            let (ttl, names) = desired
+                .entry((reverse_zone, subname))
+                .or_insert((rrset.ttl, vec![]));
+            *ttl = (*ttl).min(rrset.ttl);
+            if !names.contains(&hostname) {
+                names.push(hostname.clone());
+            }
+        }
+    }
+    let mut current: BTreeMap<&str, Vec<RrsetState>> = BTreeMap::new();
+    for (reverse_zone, _) in desired.keys() {
+        if !current.contains_key(reverse_zone) {
+            let rrsets = super::rrsets(client, reverse_zone).await?;
+            current.insert(reverse_zone, rrsets.iter().map(RrsetState::from).collect());
+        }
+    }
+    let suffix = format!(".{}", reverse::fqdn(zone));
+    for ((reverse_zone, subname), (ttl, names)) in desired {
+        let before = current[reverse_zone]
+            .iter()
+            .find(|rrset| rrset.subname == subname && rrset.r#type == "PTR")
+            .cloned();
+        let mut records: Vec<String> = before
+            .iter()
+            .flat_map(|before| &before.records)
+            .filter(|record| {
+                let record = reverse::fqdn(record);
+                record != reverse::fqdn(zone) && !record.ends_with(&suffix)
+            })
+            .cloned()
+            .collect();
+        records.extend(names);
+        let after = RrsetState {
+            subname,
+            r#type: String::from("PTR"),
+            ttl: before.as_ref().map_or(ttl, |before| before.ttl),
+            records,
+        };
+        plan.changes.extend(changes(reverse_zone, before, after));
+    }
+    Ok(plan)
+}
+
+// The change to the new state, none if the rrset is in that state already.
+fn changes(zone: &str, before: Option<RrsetState>, after: RrsetState) -> Vec<RrsetChange> {
+    match conflict::same_state(before.as_ref(), Some(&after)) {
+        true => vec![],
+        false => vec![RrsetChange {
+            domain: zone.to_string(),
+            before,
+            after,
+        }],
+    }
+}
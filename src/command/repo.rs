@@ -0,0 +1,155 @@
+//! Commands keeping the zones of domains as files in a directory.
+
+use super::{Error, Result};
+use crate::api::Client;
+use crate::changeset::{self, RrsetChange, RrsetState};
+use crate::repo::{self, State};
+use crate::search;
+use crate::zone;
+use std::fs;
+use std::path::Path;
+
+/// Initializes the directory as repository of the domains matching the glob patterns, all without any.
+pub fn init(dir: &Path, domains: &[String]) -> Result<()> {
+    if let Some(error) = domains
+        .iter()
+        .find_map(|pattern| glob::Pattern::new(pattern).err())
+    {
+        return Err(Error::Invalid(format!("Invalid domain pattern: {error}")));
+    }
+    if State::exists(dir) {
+        return Err(Error::Invalid(format!(
+            "{} is already a zone repository",
+            dir.display()
+        )));
+    }
+    fs::create_dir_all(dir)
+        .map_err(|error| Error::Io(format!("Failed to create {}: {error}", dir.display())))?;
+    let state = State {
+        domains: match domains.is_empty() {
+            true => vec![String::from("*")],
+            false => domains.to_vec(),
+        },
+        ..Default::default()
+    };
+    state.save(dir).map_err(Error::Io)
+}
+
+/// The outcome of [`pull`].
+#[derive(Default)]
+pub struct Pull {
+    /// Domains whose zone file has been written
+    pub pulled: Vec<String>,
+    /// Domains which no longer exist, their files are left for the user to remove
+    pub gone: Vec<String>,
+    /// Domains which could not be pulled
+    pub failed: Vec<Error>,
+}
+
+/// Writes the zone files of all domains of the repository.
+pub async fn pull(client: &Client, dir: &Path) -> Result<Pull> {
+    let mut state = State::load(dir).map_err(Error::Io)?;
+    let patterns = state
+        .domains
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|error| Error::Invalid(format!("Invalid domain pattern: {error}")))?;
+    let mut domains = search::domain_names(client, None)
+        .await
+        .map_err(Error::api("Failed to list domains"))?;
+    domains.retain(|domain| patterns.iter().any(|pattern| pattern.matches(domain)));
+    let mut outcome = Pull::default();
+    for domain in &domains {
+        match pull_zone(client, dir, domain).await {
+            Ok(touched) => {
+                state.touched.insert(domain.clone(), touched);
+                outcome.pulled.push(domain.clone());
+            }
+            Err(error) => outcome.failed.push(error),
+        }
+    }
+    state.touched.retain(|domain, _| {
+        let exists = domains.contains(domain);
+        if !exists {
+            outcome.gone.push(domain.clone());
+        }
+        exists
+    });
+    state.save(dir).map_err(Error::Io)?;
+    Ok(outcome)
+}
+
+/// Writes the zone file of the domain and returns its touched timestamp.
+pub async fn pull_zone(client: &Client, dir: &Path, domain: &str) -> Result<String> {
+    // The domain is read first, so changes in between make the timestamp older, never newer
+    let touched = super::domain::get(client, domain).await?.touched;
+    let rrsets = super::rrsets(client, domain).await?;
+    let path = repo::zone_path(dir, domain);
+    let zone = zone::render(domain, &rrsets, &[], zone::ZoneOptions::default());
+    fs::write(&path, zone)
+        .map_err(|error| Error::Io(format!("Failed to write {}: {error}", path.display())))?;
+    Ok(touched)
+}
+
+/// The changes pushing the zone files of a repository.
+pub struct PushPlan {
+    pub changes: Vec<RrsetChange>,
+    /// The domains with changes, which have to be pulled again by [`finish_push`]
+    pub domains: Vec<String>,
+}
+
+/// Computes the changes applying the zone files to the domains.
+///
+/// Fails if a changed domain has been changed by someone else since the last pull,
+/// as pushing would silently revert those changes.
+pub async fn plan_push(client: &Client, dir: &Path) -> Result<PushPlan> {
+    let state = State::load(dir).map_err(Error::Io)?;
+    let files = repo::zone_files(dir).map_err(Error::Io)?;
+    let mut plan = PushPlan {
+        changes: vec![],
+        domains: vec![],
+    };
+    for (domain, path) in files {
+        let Some(pulled) = state.touched.get(&domain) else {
+            return Err(Error::Invalid(format!(
+                "Domain {domain} has not been pulled, only existing domains can be pushed"
+            )));
+        };
+        let desired = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|content| zone::parse(&domain, &content))
+            .map_err(|error| {
+                Error::Invalid(format!("Invalid zone file {}: {error}", path.display()))
+            })?;
+        let current: Vec<RrsetState> = super::rrsets(client, &domain)
+            .await?
+            .iter()
+            .filter(|rrset| !zone::DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+            .map(RrsetState::from)
+            .collect();
+        let changes = changeset::diff(&domain, &current, &desired);
+        if changes.is_empty() {
+            continue;
+        }
+        let touched = super::domain::get(client, &domain).await?.touched;
+        if &touched != pulled {
+            return Err(Error::Conflict(format!(
+                "Domain {domain} has been changed since the last pull ({touched} instead of {pulled}), pull and reapply the changes first"
+            )));
+        }
+        plan.changes.extend(changes);
+        plan.domains.push(domain);
+    }
+    Ok(plan)
+}
+
+/// Rewrites the zone files of the pushed domains in their canonical form with the new timestamps.
+pub async fn finish_push(client: &Client, dir: &Path, domains: &[String]) -> Result<()> {
+    let mut state = State::load(dir).map_err(Error::Io)?;
+    for domain in domains {
+        let touched = pull_zone(client, dir, domain).await?;
+        state.touched.insert(domain.clone(), touched);
+    }
+    state.save(dir).map_err(Error::Io)
+}
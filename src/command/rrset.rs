@@ -0,0 +1,415 @@
+//! Commands reading and changing the rrsets of domains.
+
+use super::{Error, Result};
+use crate::api::Client;
+use crate::changeset::{self, RrsetChange, RrsetState, UndoFile};
+use crate::conflict::{self, Timestamp};
+use crate::migrate::{Migration, Step};
+use crate::rrset_name::{self, RrsetName};
+use crate::search::{self, RrsetFilter};
+use desec_api::rrset::ResourceRecordSet;
+use glob::Pattern;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Which rrsets of a domain [`list`] returns and in which order.
+#[derive(Default)]
+pub struct ListQuery {
+    /// Only rrsets of this subname, `@` for the zone apex
+    pub subname: Option<String>,
+    /// Only rrsets of this type
+    pub r#type: Option<String>,
+    /// Only rrsets with a record matching the regex
+    pub value: Option<Regex>,
+    /// The order of the rrsets, as returned by the API without
+    pub sort: Option<SortKey>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SortKey {
+    Subname,
+    Type,
+    Ttl,
+}
+
+/// Returns the rrsets of the domain matching the query.
+pub async fn list(
+    client: &Client,
+    domain: &str,
+    query: &ListQuery,
+) -> Result<Vec<ResourceRecordSet>> {
+    let subname = query
+        .subname
+        .as_deref()
+        .map(|subname| rrset_name::subname(subname).unwrap_or_default());
+    let result = match (subname, &query.r#type) {
+        // Subname and type name a single rrset, which is requested directly
+        (Some(subname), Some(r#type)) => {
+            let subname = Some(subname).filter(|subname| !subname.is_empty());
+            match client.rrset().get_rrset(domain, subname, r#type).await {
+                Ok(rrset) => Ok(vec![rrset]),
+                // The domain exists but has no such rrset
+                Err(desec_api::Error::NotFound) => {
+                    super::domain::get(client, domain).await?;
+                    Ok(vec![])
+                }
+                Err(error) => Err(error),
+            }
+        }
+        (Some(subname), None) => client.rrset().get_rrsets_by_subname(domain, subname).await,
+        (None, Some(r#type)) => client.rrset().get_rrsets_by_type(domain, r#type).await,
+        (None, None) => client.rrset().get_rrsets(domain).await,
+    };
+    let mut rrsets = match result {
+        Ok(rrsets) => rrsets,
+        Err(desec_api::Error::NotFound) => return Err(Error::NotFound(format!("Domain {domain}"))),
+        Err(error) => {
+            return Err(Error::Api(
+                format!("Failed to get rrsets of domain {domain}"),
+                error,
+            ))
+        }
+    };
+    let filter = RrsetFilter {
+        value: query.value.clone(),
+        types: query.r#type.iter().cloned().collect(),
+        ..Default::default()
+    };
+    rrsets.retain(|rrset| filter.matches(rrset));
+    match query.sort {
+        Some(SortKey::Subname) => rrsets.sort_by(|a, b| {
+            (search::subname(a), &a.rrset_type).cmp(&(search::subname(b), &b.rrset_type))
+        }),
+        Some(SortKey::Type) => rrsets.sort_by(|a, b| {
+            (&a.rrset_type, search::subname(a)).cmp(&(&b.rrset_type, search::subname(b)))
+        }),
+        Some(SortKey::Ttl) => rrsets.sort_by(|a, b| {
+            (a.ttl, search::subname(a), &a.rrset_type).cmp(&(
+                b.ttl,
+                search::subname(b),
+                &b.rrset_type,
+            ))
+        }),
+        None => (),
+    }
+    Ok(rrsets)
+}
+
+/// Returns a single rrset.
+pub async fn get(client: &Client, name: &RrsetName) -> Result<ResourceRecordSet> {
+    client
+        .rrset()
+        .get_rrset(&name.domain, name.subname(), &name.r#type)
+        .await
+        .map_err(|error| match error {
+            desec_api::Error::NotFound => Error::NotFound(format!("RRSet {name}")),
+            error => Error::Api(format!("Failed to get rrset {name}"), error),
+        })
+}
+
+/// Creates an rrset, fails if it exists already.
+pub async fn create(
+    client: &Client,
+    name: &RrsetName,
+    ttl: u64,
+    records: &[String],
+) -> Result<ResourceRecordSet> {
+    client
+        .rrset()
+        .create_rrset(&name.domain, name.subname(), &name.r#type, ttl, records)
+        .await
+        .map_err(|error| match error {
+            desec_api::Error::NotFound => Error::NotFound(format!("Domain {}", name.domain)),
+            error => Error::Api(format!("Creation of rrset {name} failed"), error),
+        })
+}
+
+/// Deletes an rrset, succeeds if it does not exist.
+pub async fn delete(client: &Client, name: &RrsetName) -> Result<()> {
+    client
+        .rrset()
+        .delete_rrset(&name.domain, name.subname(), &name.r#type)
+        .await
+        .map_err(|error| match error {
+            desec_api::Error::NotFound => Error::NotFound(format!("Domain {}", name.domain)),
+            error => Error::Api(format!("Deletion of rrset {name} failed"), error),
+        })
+}
+
+/// Creates the filter of a search, compiling the value regex and the subname glob pattern.
+pub fn search_filter(
+    value: Option<&str>,
+    types: &[String],
+    subname: Option<&str>,
+    min_ttl: Option<u64>,
+    max_ttl: Option<u64>,
+) -> Result<RrsetFilter> {
+    let value = value
+        .map(Regex::new)
+        .transpose()
+        .map_err(|error| Error::Invalid(format!("Invalid value regex: {error}")))?;
+    let subname = subname
+        .map(Pattern::new)
+        .transpose()
+        .map_err(|error| Error::Invalid(format!("Invalid subname pattern: {error}")))?;
+    Ok(RrsetFilter {
+        value,
+        types: types.to_vec(),
+        subname,
+        min_ttl,
+        max_ttl,
+    })
+}
+
+/// Returns the rrsets of the domains matching the filter, see [`search::search`].
+pub async fn search(
+    client: &Client,
+    domains: &[String],
+    filter: &RrsetFilter,
+    parallel: usize,
+) -> Vec<(String, Result<Vec<ResourceRecordSet>>)> {
+    search::search(client, domains, filter, parallel)
+        .await
+        .into_iter()
+        .map(|(domain, result)| {
+            let request = format!("Failed to get rrsets of domain {domain}");
+            (domain, result.map_err(Error::api(request)))
+        })
+        .collect()
+}
+
+/// Computes the changes replacing a value in the records of the domains.
+///
+/// Fails if the rrsets of any domain cannot be read, as replacing in only
+/// some of the domains would leave a half migrated state.
+pub async fn plan_replace(
+    client: &Client,
+    domains: &[String],
+    from: &str,
+    to: &str,
+    types: &[String],
+    parallel: usize,
+) -> Result<Vec<RrsetChange>> {
+    let filter = RrsetFilter {
+        value: Regex::new(&regex::escape(from)).ok(),
+        types: types.to_vec(),
+        ..Default::default()
+    };
+    let mut changes = vec![];
+    for (domain, result) in search::search(client, domains, &filter, parallel).await {
+        let rrsets = result.map_err(Error::api(format!(
+            "Failed to get rrsets of domain {domain}, nothing has been changed"
+        )))?;
+        changes.extend(
+            rrsets
+                .iter()
+                .filter_map(|rrset| changeset::replace_in(rrset, from, to)),
+        );
+    }
+    Ok(changes)
+}
+
+/// The undo file written by default, named after the current time.
+pub fn default_undo_file() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    PathBuf::from(format!("desec-undo-{timestamp}.json"))
+}
+
+/// Reads an undo file written by a previous change.
+pub fn read_undo_file(path: &Path) -> Result<UndoFile> {
+    fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|error| error.to_string()))
+        .map_err(|error| {
+            Error::Io(format!(
+                "Failed to read undo file {}: {error}",
+                path.display()
+            ))
+        })
+}
+
+/// Computes the changes restoring the rrsets saved in the undo file.
+pub async fn plan_restore(client: &Client, undo: UndoFile) -> Result<Vec<RrsetChange>> {
+    let mut changes = vec![];
+    for (domain, states) in undo.domains {
+        let current = super::rrsets(client, &domain).await?;
+        for state in states {
+            let before = current
+                .iter()
+                .map(RrsetState::from)
+                .find(|rrset| rrset.subname == state.subname && rrset.r#type == state.r#type);
+            if before.as_ref() == Some(&state) || (before.is_none() && state.records.is_empty()) {
+                continue;
+            }
+            changes.push(RrsetChange {
+                domain: domain.clone(),
+                before,
+                after: state,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Writes the undo file if given and then the changes, see [`sync`].
+/// The undo file is removed again if nothing has been written.
+pub async fn apply(
+    client: &Client,
+    changes: &[RrsetChange],
+    undo_file: Option<&Path>,
+    since: Option<&Timestamp>,
+) -> Result<Vec<(String, Result<usize>)>> {
+    if let Some(path) = undo_file {
+        serde_json::to_string_pretty(&UndoFile::new(changes))
+            .map_err(|error| error.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|error| error.to_string()))
+            .map_err(|error| {
+                Error::Io(format!(
+                    "Failed to write undo file {}, nothing has been changed: {error}",
+                    path.display()
+                ))
+            })?;
+    }
+    let results = sync(client, changes, since).await;
+    if let (Err(_), Some(path)) = (&results, undo_file) {
+        // Nothing has been written, so the undo file would be misleading
+        let _ = fs::remove_file(path);
+    }
+    results
+}
+
+/// Writes the changes with one bulk request per domain, so the changes of a domain
+/// are either all applied or none of them.
+///
+/// Before the first write every domain is checked for concurrent changes, i.e. that
+/// its rrsets still match the state the changes are based on and, if given, that it
+/// has not been changed since the timestamp. A conflict leaves all domains unchanged.
+/// Returns the number of changed rrsets or the error of each domain.
+pub async fn sync(
+    client: &Client,
+    changes: &[RrsetChange],
+    since: Option<&Timestamp>,
+) -> Result<Vec<(String, Result<usize>)>> {
+    let domains = changeset::by_domain(changes);
+    for domain in domains.keys() {
+        if let Some(since) = since {
+            conflict::check_unchanged_since(client, domain, since).await?;
+        }
+        conflict::check_unchanged(client, domain, changes).await?;
+    }
+    let mut results = vec![];
+    for (domain, rrsets) in domains {
+        let result = client
+            .rrset()
+            .patch_rrsets(&domain, &rrsets)
+            .await
+            .map(|_| rrsets.len())
+            .map_err(Error::api(format!(
+                "Changing the rrsets of {domain} failed"
+            )));
+        results.push((domain, result));
+    }
+    Ok(results)
+}
+
+/// Starts a migration of the rrset to the records, see [`crate::migrate`].
+pub async fn plan_migration(
+    client: &Client,
+    name: &RrsetName,
+    records: &[String],
+) -> Result<Migration> {
+    let original = RrsetState::from(&get(client, name).await?);
+    let minimum_ttl = super::domain::get(client, &name.domain).await?.minimum_ttl;
+    Ok(Migration::new(
+        &name.domain,
+        original,
+        records,
+        minimum_ttl.into(),
+    ))
+}
+
+/// Resumes the migration saved in the state file, or plans a new one without state file.
+/// Returns whether the migration is resumed.
+pub async fn load_migration(
+    client: &Client,
+    name: &RrsetName,
+    records: &[String],
+    path: &Path,
+) -> Result<(Migration, bool)> {
+    match Migration::load(path).map_err(Error::Io)? {
+        Some(migration) if migration.is_for(name, records) => Ok((migration, true)),
+        Some(_) => Err(Error::Conflict(format!(
+            "{} belongs to another migration, finish it or remove the file",
+            path.display()
+        ))),
+        None => Ok((plan_migration(client, name, records).await?, false)),
+    }
+}
+
+/// The progress of a migration reported by [`migrate`].
+pub enum MigrationEvent {
+    /// Waiting the given seconds before the next step
+    Waiting(u64),
+    /// The step has been written and saved
+    Written(Step),
+}
+
+/// Runs the migration to its end, saving its progress in the state file after each step.
+/// The state file is removed once the migration is done.
+pub async fn migrate(
+    client: &Client,
+    migration: &mut Migration,
+    path: &Path,
+    since: Option<&Timestamp>,
+    mut progress: impl FnMut(&Migration, MigrationEvent),
+) -> Result<()> {
+    migration.save(path).map_err(Error::Io)?;
+    while migration.next_change().is_some() {
+        let wait = migration.wait();
+        if wait > 0 {
+            progress(migration, MigrationEvent::Waiting(wait));
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+        }
+        migrate_step(client, migration, since).await?;
+        match migration.step {
+            Step::Restored => fs::remove_file(path).map_err(|error| {
+                Error::Io(format!("Failed to remove {}: {error}", path.display()))
+            })?,
+            _ => migration.save(path).map_err(Error::Io)?,
+        }
+        progress(migration, MigrationEvent::Written(migration.step));
+    }
+    Ok(())
+}
+
+/// Writes the next step of the migration and advances it.
+///
+/// A step is not written again if the rrset is in its resulting state already,
+/// e.g. because an interrupted run wrote it without saving the progress.
+pub async fn migrate_step(
+    client: &Client,
+    migration: &mut Migration,
+    since: Option<&Timestamp>,
+) -> Result<()> {
+    let Some(change) = migration.next_change() else {
+        return Ok(());
+    };
+    let name = RrsetName {
+        domain: migration.domain.clone(),
+        subname: change.after.subname.clone(),
+        r#type: change.after.r#type.clone(),
+    };
+    let current = RrsetState::from(&get(client, &name).await?);
+    if !conflict::same_state(Some(&current), Some(&change.after)) {
+        for (_, result) in sync(client, &[change], since).await? {
+            result?;
+        }
+    }
+    migration.advance();
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! Commands managing the tokens of the account and their policies.
+
+use super::{Error, Result};
+use crate::api::token::{PolicyFields, TokenFields};
+use crate::api::{Client, Patch, DEFAULT_ALLOWED_SUBNETS};
+use desec_api::token::{Token, TokenPolicy};
+
+// Maps the not found error of a request for the token or policy.
+fn not_found(what: String, request: String) -> impl FnOnce(desec_api::Error) -> Error {
+    move |error| match error {
+        desec_api::Error::NotFound => Error::NotFound(what),
+        error => Error::Api(request, error),
+    }
+}
+
+/// The changes of a `token patch`, fields which are None are left unchanged.
+#[derive(Default)]
+pub struct TokenPatch {
+    pub name: Option<String>,
+    pub allowed_subnets: Patch<Vec<String>>,
+    pub perm_manage_tokens: Option<bool>,
+    pub max_age: Patch<String>,
+    pub max_unused_period: Patch<String>,
+}
+
+impl TokenPatch {
+    /// The fields sent for the patch.
+    pub fn fields(self) -> TokenFields {
+        let allowed_subnets = match self.allowed_subnets {
+            // The API does not accept null for the subnets, so we send its default instead
+            Patch::Clear => Some(DEFAULT_ALLOWED_SUBNETS.map(String::from).to_vec()),
+            subnets => subnets.resolve(None),
+        };
+        TokenFields {
+            name: self.name,
+            allowed_subnets,
+            perm_manage_tokens: self.perm_manage_tokens,
+            max_age: self.max_age,
+            max_unused_period: self.max_unused_period,
+        }
+    }
+}
+
+/// The changes of a `policy patch`, fields which are unchanged keep their current value.
+#[derive(Default)]
+pub struct PolicyPatch {
+    pub domain: Patch<String>,
+    pub subname: Patch<String>,
+    pub r#type: Patch<String>,
+    pub perm_write: Option<bool>,
+}
+
+/// A policy patch resolved against the current policy.
+pub struct PolicyPlan {
+    pub current: PolicyFields,
+    pub fields: PolicyFields,
+}
+
+pub async fn list(client: &Client) -> Result<Vec<Token>> {
+    client
+        .token()
+        .list()
+        .await
+        .map_err(Error::api("Failed to list tokens"))
+}
+
+pub async fn get(client: &Client, token_id: &str) -> Result<Token> {
+    client.token().get(token_id).await.map_err(not_found(
+        format!("Token {token_id}"),
+        format!("Failed to get token {token_id}"),
+    ))
+}
+
+/// Creates a token, the response holds its secret which is not shown again.
+pub async fn create(client: &Client, fields: TokenFields) -> Result<Token> {
+    client
+        .token()
+        .create(fields)
+        .await
+        .map_err(Error::api("Creation of the token failed"))
+}
+
+pub async fn patch(client: &Client, token_id: &str, patch: TokenPatch) -> Result<Token> {
+    client
+        .token()
+        .patch(token_id, patch.fields())
+        .await
+        .map_err(not_found(
+            format!("Token {token_id}"),
+            format!("Failed to patch token {token_id}"),
+        ))
+}
+
+pub async fn delete(client: &Client, token_id: &str) -> Result<()> {
+    client
+        .token()
+        .delete(token_id)
+        .await
+        .map_err(Error::api(format!("Deletion of token {token_id} failed")))
+}
+
+pub async fn list_policies(client: &Client, token_id: &str) -> Result<Vec<TokenPolicy>> {
+    client
+        .token()
+        .list_policies(token_id)
+        .await
+        .map_err(not_found(
+            format!("Token {token_id}"),
+            format!("Failed to list the policies of token {token_id}"),
+        ))
+}
+
+pub async fn get_policy(client: &Client, token_id: &str, policy_id: &str) -> Result<TokenPolicy> {
+    client
+        .token()
+        .get_policy(token_id, policy_id)
+        .await
+        .map_err(not_found(
+            format!("Policy {policy_id} of token {token_id}"),
+            format!("Failed to get policy {policy_id} of token {token_id}"),
+        ))
+}
+
+pub async fn create_policy(
+    client: &Client,
+    token_id: &str,
+    fields: PolicyFields,
+) -> Result<TokenPolicy> {
+    client
+        .token()
+        .create_policy(token_id, fields)
+        .await
+        .map_err(Error::api(format!(
+            "Creation of a policy of token {token_id} failed"
+        )))
+}
+
+/// Resolves the patch against the current policy, as desec_api sends every field of it.
+pub async fn plan_policy_patch(
+    client: &Client,
+    token_id: &str,
+    policy_id: &str,
+    patch: PolicyPatch,
+) -> Result<PolicyPlan> {
+    let current = PolicyFields::from(&get_policy(client, token_id, policy_id).await?);
+    let fields = PolicyFields {
+        domain: patch.domain.resolve(current.domain.clone()),
+        subname: patch.subname.resolve(current.subname.clone()),
+        r#type: patch.r#type.resolve(current.r#type.clone()),
+        perm_write: patch.perm_write.unwrap_or(current.perm_write),
+    };
+    Ok(PolicyPlan { current, fields })
+}
+
+pub async fn patch_policy(
+    client: &Client,
+    token_id: &str,
+    policy_id: &str,
+    fields: PolicyFields,
+) -> Result<TokenPolicy> {
+    client
+        .token()
+        .patch_policy(token_id, policy_id, fields)
+        .await
+        .map_err(Error::api(format!(
+            "Failed to patch policy {policy_id} of token {token_id}"
+        )))
+}
+
+pub async fn delete_policy(client: &Client, token_id: &str, policy_id: &str) -> Result<()> {
+    client
+        .token()
+        .delete_policy(token_id, policy_id)
+        .await
+        .map_err(Error::api(format!(
+            "Deletion of policy {policy_id} of token {token_id} failed"
+        )))
+}
@@ -0,0 +1,319 @@
+use crate::cli::Cli;
+use clap::{ArgMatches, CommandFactory};
+use clap_complete::engine::ValueCompleter;
+use clap_complete::CompletionCandidate;
+use desec_api::Error;
+use desec_cli::api::Client;
+use desec_cli::rrset_name;
+use desec_cli::template;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsStr;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use std::{env, fs};
+use tokio::runtime::Handle;
+
+/// How long values fetched from the API are reused for further completions
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Record types supported by deSEC
+pub const RECORD_TYPES: &[&str] = &[
+    "A",
+    "AAAA",
+    "AFSDB",
+    "APL",
+    "CAA",
+    "CDNSKEY",
+    "CDS",
+    "CERT",
+    "CNAME",
+    "DHCID",
+    "DNAME",
+    "DNSKEY",
+    "DLV",
+    "DS",
+    "EUI48",
+    "EUI64",
+    "HINFO",
+    "HTTPS",
+    "KX",
+    "L32",
+    "L64",
+    "LOC",
+    "LP",
+    "MX",
+    "NAPTR",
+    "NID",
+    "NS",
+    "OPENPGPKEY",
+    "PTR",
+    "RP",
+    "SMIMEA",
+    "SPF",
+    "SRV",
+    "SSHFP",
+    "SVCB",
+    "TLSA",
+    "TXT",
+    "URI",
+];
+
+/// Completes the names of all domains in the account.
+pub fn domains() -> Vec<CompletionCandidate> {
+    cached("domains", |client| async move {
+        let domains = client.domain().get_domains().await?;
+        Ok(domains.into_iter().map(|d| d.name).collect::<Vec<_>>())
+    })
+    .into_iter()
+    .map(CompletionCandidate::new)
+    .collect()
+}
+
+/// Completes the subnames existing in the domain given on the command line.
+pub fn subnames() -> Vec<CompletionCandidate> {
+    let Some(domain) = leaf_arg("name") else {
+        return vec![];
+    };
+    let mut subnames: Vec<String> = rrsets(&domain)
+        .into_iter()
+        .map(|(subname, _)| subname)
+        .collect();
+    subnames.dedup();
+    subnames.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Completes all record types supported by deSEC.
+pub fn record_types() -> Vec<CompletionCandidate> {
+    RECORD_TYPES.iter().map(CompletionCandidate::new).collect()
+}
+
+/// Completes the names of all templates, using their descriptions as help.
+pub fn templates() -> Vec<CompletionCandidate> {
+    template::list()
+        .into_iter()
+        .map(|(name, description)| CompletionCandidate::new(name).help(Some(description.into())))
+        .collect()
+}
+
+/// Completes the ids of all tokens, using their names as help.
+pub fn tokens() -> Vec<CompletionCandidate> {
+    cached("tokens", |client| async move {
+        let tokens = client.token().list().await?;
+        Ok(tokens
+            .into_iter()
+            .map(|t| (t.id, t.name))
+            .collect::<Vec<_>>())
+    })
+    .into_iter()
+    .map(|(id, name)| CompletionCandidate::new(id).help(Some(name.into())))
+    .collect()
+}
+
+/// Completes the policy ids of the token given on the command line.
+pub fn policies() -> Vec<CompletionCandidate> {
+    let Some(token_id) = leaf_arg("token_id") else {
+        return vec![];
+    };
+    cached(&format!("policies-{token_id}"), |client| async move {
+        let policies = client.token().list_policies(&token_id).await?;
+        Ok(policies
+            .into_iter()
+            .map(|p| {
+                let scope = [p.domain, p.subname, p.r#type]
+                    .map(|field| field.unwrap_or_else(|| String::from("*")))
+                    .join(" ");
+                let access = if p.perm_write { "write" } else { "read" };
+                (p.id, format!("{scope} ({access})"))
+            })
+            .collect::<Vec<_>>())
+    })
+    .into_iter()
+    .map(|(id, help)| CompletionCandidate::new(id).help(Some(help.into())))
+    .collect()
+}
+
+/// Completes the words naming an rrset, i.e. `<DOMAIN> <SUBNAME> <TYPE>` or `<FQDN> <TYPE>`.
+#[derive(Clone, Copy, Default)]
+pub struct RrsetNameCompleter {
+    /// Whether only the types of existing rrsets are completed
+    existing: bool,
+}
+
+impl RrsetNameCompleter {
+    /// Completes the name of a new rrset.
+    pub fn new() -> Self {
+        RrsetNameCompleter { existing: false }
+    }
+
+    /// Completes the name of an existing rrset.
+    pub fn existing() -> Self {
+        RrsetNameCompleter { existing: true }
+    }
+}
+
+impl ValueCompleter for RrsetNameCompleter {
+    fn complete(&self, current: &OsStr) -> Vec<CompletionCandidate> {
+        self.complete_at(0, current)
+    }
+
+    fn complete_at(&self, arg_index: usize, current: &OsStr) -> Vec<CompletionCandidate> {
+        let words = leaf_args("rrset");
+        let current = current.to_string_lossy();
+        let rrsets = match words.first() {
+            Some(domain) if arg_index > 0 => rrsets(domain),
+            _ => vec![],
+        };
+        let candidates: Vec<String> = match arg_index {
+            0 => domains()
+                .into_iter()
+                .map(|c| c.get_value().to_string_lossy().into_owned())
+                .collect(),
+            // A name which is not a domain of the account is a fully qualified name followed by the type
+            1 if rrsets.is_empty() => RECORD_TYPES.iter().map(|t| t.to_string()).collect(),
+            1 => {
+                let mut subnames: Vec<String> =
+                    rrsets.into_iter().map(|(subname, _)| subname).collect();
+                subnames.dedup();
+                subnames
+            }
+            2 if rrsets.is_empty() => vec![],
+            2 if self.existing => rrsets
+                .into_iter()
+                .filter(|(subname, _)| Some(subname) == words.get(1))
+                .map(|(_, r#type)| r#type)
+                .collect(),
+            2 => RECORD_TYPES.iter().map(|t| t.to_string()).collect(),
+            _ => vec![],
+        };
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(current.as_ref()))
+            .map(CompletionCandidate::new)
+            .collect()
+    }
+}
+
+// Subname and type of all rrsets of the domain.
+fn rrsets(domain: &str) -> Vec<(String, String)> {
+    let domain = domain.to_string();
+    let mut rrsets = cached(&format!("rrsets-{domain}"), |client| async move {
+        let rrsets = client.rrset().get_rrsets(&domain).await?;
+        Ok(rrsets
+            .into_iter()
+            .map(|r| {
+                let subname = r.subname.unwrap_or_default();
+                (
+                    rrset_name::display_subname(&subname).to_string(),
+                    r.rrset_type,
+                )
+            })
+            .collect::<Vec<_>>())
+    });
+    rrsets.sort();
+    rrsets
+}
+
+// Returns the value of the given argument of the innermost subcommand
+// from the (incomplete) command line which is currently being completed.
+fn leaf_arg(id: &str) -> Option<String> {
+    // The shell integration calls us with `-- <words of the command line>`
+    let words: Vec<String> = env::args().skip_while(|arg| arg != "--").skip(1).collect();
+    let mut matches: ArgMatches = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(words)
+        .ok()?;
+    while let Some((_, sub_matches)) = matches.subcommand() {
+        matches = sub_matches.clone();
+    }
+    matches.try_get_one::<String>(id).ok().flatten().cloned()
+}
+
+// Returns all values of the given argument of the innermost subcommand.
+fn leaf_args(id: &str) -> Vec<String> {
+    let words: Vec<String> = env::args().skip_while(|arg| arg != "--").skip(1).collect();
+    let Ok(mut matches) = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(words)
+    else {
+        return vec![];
+    };
+    while let Some((_, sub_matches)) = matches.subcommand() {
+        matches = sub_matches.clone();
+    }
+    matches
+        .try_get_many::<String>(id)
+        .ok()
+        .flatten()
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+// Returns the cached value for the given key or fetches and caches it.
+// Completion must never fail loudly, so errors result in no candidates.
+fn cached<T, F, Fut>(key: &str, fetch: F) -> T
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: FnOnce(Client) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    // Only tokens are supported, logging in with credentials on every
+    // completion would create a new token each time.
+    let Ok(token) = env::var("DESEC_API_TOKEN") else {
+        return T::default();
+    };
+    let path = cache_path(&token, key);
+    if let Some(value) = path.as_ref().and_then(read_cache) {
+        return value;
+    }
+    let Ok(mut client) = Client::new(&token) else {
+        return T::default();
+    };
+    // Waiting for throttled requests would block the shell
+    client.set_retry(false);
+    let Ok(handle) = Handle::try_current() else {
+        return T::default();
+    };
+    let Ok(value) = tokio::task::block_in_place(|| handle.block_on(fetch(client))) else {
+        return T::default();
+    };
+    if let Some(path) = path {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&value) {
+            let _ = fs::write(path, json);
+        }
+    }
+    value
+}
+
+// The cache is kept per token, so switching accounts does not show stale values.
+fn cache_path(token: &str, key: &str) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    let key: String = key
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    Some(
+        dirs::cache_dir()?
+            .join("desec_cli")
+            .join(format!("completion-{:x}-{key}.json", hasher.finish())),
+    )
+}
+
+fn read_cache<T: DeserializeOwned>(path: &PathBuf) -> Option<T> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    if SystemTime::now().duration_since(modified).ok()? > CACHE_TTL {
+        return None;
+    }
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
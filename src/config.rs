@@ -0,0 +1,77 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+/// Settings read from `config.yaml` in the desec_cli config directory,
+/// or from the file given in the env var DESEC_CLI_CONFIG.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Domains which cannot be deleted without `--force-protected`
+    pub protected_domains: Vec<String>,
+    /// Further accounts which can be selected by name, e.g. with `--to-profile`
+    pub profiles: BTreeMap<String, Profile>,
+    /// DNS over HTTPS resolver for lookups outside of deSEC, overridden by `--resolver`
+    pub resolver: Option<String>,
+}
+
+/// The credentials of an account selectable by name.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The API token of the account
+    pub token: Option<String>,
+    /// The env var holding the API token, which keeps it out of the config file
+    pub token_env: Option<String>,
+}
+
+impl Config {
+    /// Loads the config file, a missing file results in the default config.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        };
+        serde_yaml::from_str(&content)
+            .map_err(|error| format!("Failed to parse {}: {error}", path.display()))
+    }
+
+    /// The directory holding the config file and user supplied files
+    pub fn dir() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("desec_cli"))
+    }
+
+    fn path() -> Option<PathBuf> {
+        match env::var_os("DESEC_CLI_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(Config::dir()?.join("config.yaml")),
+        }
+    }
+
+    /// Returns the API token of the given profile.
+    pub fn profile_token(&self, name: &str) -> Result<String, String> {
+        let Some(profile) = self.profiles.get(name) else {
+            return Err(format!("Unknown profile {name}"));
+        };
+        match (&profile.token, &profile.token_env) {
+            (Some(token), _) => Ok(token.clone()),
+            (None, Some(var)) => env::var(var)
+                .map_err(|_| format!("Missing env var {var} holding the token of profile {name}")),
+            (None, None) => Err(format!("Profile {name} has neither token nor token_env")),
+        }
+    }
+
+    /// Whether the given domain is listed as protected, ignoring case and a trailing dot.
+    pub fn is_protected(&self, domain: &str) -> bool {
+        let normalize = |name: &str| name.trim_end_matches('.').to_ascii_lowercase();
+        let domain = normalize(domain);
+        self.protected_domains
+            .iter()
+            .any(|protected| normalize(protected) == domain)
+    }
+}
@@ -0,0 +1,27 @@
+use log::{error, warn};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Asks the user to type `expected` to confirm a destructive operation.
+///
+/// Without a terminal on stdin nobody can answer, so the operation is refused
+/// and the user is pointed to `--yes`.
+pub fn confirm(action: &str, expected: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        error!("Refusing to {action} without confirmation, pass --yes to skip it");
+        return false;
+    }
+    eprint!("This will {action}.\nType '{expected}' to confirm: ");
+    if io::stderr().flush().is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    if answer.trim() == expected {
+        true
+    } else {
+        warn!("Confirmation did not match, aborting");
+        false
+    }
+}
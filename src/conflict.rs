@@ -0,0 +1,315 @@
+use crate::api::Client;
+use crate::changeset::{RrsetChange, RrsetState};
+use crate::command::{self, Error};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A point in time as used by the touched fields of the API, e.g. `2024-05-04T16:24:28.660313Z`.
+#[derive(Clone, Debug)]
+pub struct Timestamp {
+    text: String,
+    /// Seconds and nanoseconds since the unix epoch, for comparison across formats and offsets
+    instant: (i64, u32),
+}
+
+// Timestamps are equal if they denote the same instant, whatever their format and offset.
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.instant == other.instant
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.instant.cmp(&other.instant))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    /// Parses an RFC 3339 timestamp with optional fractional seconds.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid timestamp {text}, expected e.g. 2024-05-04T16:24:28Z");
+        let number = |range: std::ops::Range<usize>| -> Result<i64, String> {
+            text.get(range)
+                .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|digits| digits.parse().ok())
+                .ok_or_else(error)
+        };
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if text.len() < 20
+            || separators.iter().any(|(i, c)| text.as_bytes()[*i] != *c)
+            || !matches!(text.as_bytes()[10], b'T' | b't' | b' ')
+        {
+            return Err(error());
+        }
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        let mut rest = &text[19..];
+        let mut nanos = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return Err(error());
+            }
+            let padded = format!("{:0<9}", &fraction[..digits.min(9)]);
+            nanos = padded.parse().map_err(|_| error())?;
+            rest = &fraction[digits..];
+        }
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return Err(error()),
+                };
+                let hours: i64 = rest[1..3].parse().map_err(|_| error())?;
+                let minutes: i64 = rest[4..6].parse().map_err(|_| error())?;
+                if hours > 23 || minutes > 59 {
+                    return Err(error());
+                }
+                sign * (hours * 3600 + minutes * 60)
+            }
+            _ => return Err(error()),
+        };
+        if !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return Err(error());
+        }
+        let seconds =
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+        Ok(Timestamp {
+            text: text.to_string(),
+            instant: (seconds, nanos),
+        })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    /// Formats the time in UTC with microseconds like the API does.
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = since_epoch.as_secs() as i64;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let second_of_day = seconds.rem_euclid(86400);
+        let text = format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+            second_of_day / 3600,
+            second_of_day / 60 % 60,
+            second_of_day % 60,
+            since_epoch.subsec_micros()
+        );
+        Timestamp {
+            text,
+            instant: (seconds, since_epoch.subsec_nanos()),
+        }
+    }
+}
+
+// Number of days of the month in the proleptic Gregorian calendar.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since the unix epoch of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Date in the proleptic Gregorian calendar of the given days since the unix epoch.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Fails if the domain has been changed after the given time.
+pub async fn check_unchanged_since(
+    client: &Client,
+    domain: &str,
+    since: &Timestamp,
+) -> command::Result<()> {
+    let touched = client
+        .domain()
+        .get_domain(domain)
+        .await
+        .map_err(Error::api(format!("Failed to get domain {domain}")))?
+        .touched;
+    check_touched(domain, &touched, since)
+}
+
+/// Fails if the touched timestamp of the domain is after the given time.
+pub fn check_touched(domain: &str, touched: &str, since: &Timestamp) -> command::Result<()> {
+    let touched: Timestamp = touched.parse().map_err(Error::Invalid)?;
+    match touched > *since {
+        true => Err(Error::Conflict(format!(
+            "Conflict: domain {domain} has been changed at {touched}, after {since}"
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// Fails if any rrset of the changes has been changed by someone else since it was read,
+/// i.e. its current state differs from the state the change is based on.
+pub async fn check_unchanged(
+    client: &Client,
+    domain: &str,
+    changes: &[RrsetChange],
+) -> command::Result<()> {
+    let current = client
+        .rrset()
+        .get_rrsets(domain)
+        .await
+        .map_err(Error::api(format!(
+            "Failed to get rrsets of domain {domain}"
+        )))?;
+    for change in changes.iter().filter(|change| change.domain == domain) {
+        let current = current.iter().map(RrsetState::from).find(|state| {
+            state.subname == change.after.subname && state.r#type == change.after.r#type
+        });
+        if !same_state(current.as_ref(), change.before.as_ref()) {
+            return Err(Error::Conflict(format!(
+                "Conflict: rrset {} {}.{domain} has been changed since it was read, nothing has been changed",
+                change.after.r#type,
+                crate::rrset_name::display_subname(&change.after.subname),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Compares rrsets ignoring the order of their records.
+pub(crate) fn same_state(a: Option<&RrsetState>, b: Option<&RrsetState>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            let sorted = |state: &RrsetState| {
+                let mut records = state.records.clone();
+                records.sort();
+                records
+            };
+            a.ttl == b.ttl && sorted(a) == sorted(b)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn timestamp(text: &str) -> Timestamp {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_valid() {
+        assert_eq!(timestamp("1970-01-01T00:00:00Z").instant, (0, 0));
+        assert_eq!(
+            timestamp("2024-02-29T12:00:00Z").to_string(),
+            "2024-02-29T12:00:00Z"
+        );
+        assert_eq!(timestamp("2000-02-29 23:59:59z").instant.0, 951868799);
+    }
+
+    #[test]
+    fn parse_invalid_dates() {
+        for text in [
+            "2024-02-30T00:00:00Z",
+            "2023-02-29T00:00:00Z",
+            "1900-02-29T00:00:00Z",
+            "2024-04-31T00:00:00Z",
+            "2024-13-01T00:00:00Z",
+            "2024-00-01T00:00:00Z",
+            "2024-01-00T00:00:00Z",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:60:00Z",
+            "2024-01-01T00:00:61Z",
+            "2024-01-01T00:00:00+24:00",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00.Z",
+            "2024-01-01",
+        ] {
+            assert!(text.parse::<Timestamp>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn parse_fractional_seconds() {
+        assert_eq!(timestamp("2024-05-04T16:24:28.5Z").instant.1, 500_000_000);
+        assert_eq!(
+            timestamp("2024-05-04T16:24:28.660313Z").instant.1,
+            660_313_000
+        );
+        // Digits beyond nanoseconds are ignored
+        assert_eq!(
+            timestamp("2024-05-04T16:24:28.1234567899Z").instant.1,
+            123_456_789
+        );
+    }
+
+    #[test]
+    fn parse_offsets() {
+        let utc = timestamp("2024-05-04T16:24:28Z");
+        assert_eq!(timestamp("2024-05-04T18:24:28+02:00").instant, utc.instant);
+        assert_eq!(timestamp("2024-05-04T11:54:28-04:30").instant, utc.instant);
+        assert_eq!(timestamp("2024-05-05T00:24:28+08:00").instant, utc.instant);
+    }
+
+    #[test]
+    fn ordering() {
+        let utc = timestamp("2024-05-04T16:24:28Z");
+        let offset = timestamp("2024-05-04T18:24:28.000+02:00");
+        assert_eq!(utc, offset);
+        assert_eq!(utc.partial_cmp(&offset), Some(std::cmp::Ordering::Equal));
+        assert!(timestamp("2024-05-04T16:24:28.000001Z") > utc);
+        assert!(timestamp("2024-05-04T17:24:28+02:00") < utc);
+        assert!(timestamp("2023-12-31T23:59:59.999Z") < timestamp("2024-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn from_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1714839868, 660_313_000);
+        let timestamp = Timestamp::from(time);
+        assert_eq!(timestamp.to_string(), "2024-05-04T16:24:28.660313Z");
+        assert_eq!(timestamp, "2024-05-04T16:24:28.660313Z".parse().unwrap());
+    }
+}
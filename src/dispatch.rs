@@ -0,0 +1,356 @@
+use super::*;
+use futures::future::LocalBoxFuture;
+use std::future::Future;
+
+/// What every command runs with: the parsed arguments, holding the output
+/// settings and flags like `--quiet` and `--dry-run`, and the API client.
+pub struct Context<'a> {
+    pub cli: &'a Cli,
+    client: Option<Client>,
+}
+
+impl<'a> Context<'a> {
+    /// Creates the context, with a client logged in from the env vars if the command requires it.
+    pub async fn new(cli: &'a Cli, requires_auth: bool) -> Option<Context<'a>> {
+        let client = match requires_auth {
+            true => Some(create_client(cli).await?),
+            false => None,
+        };
+        Some(Context { cli, client })
+    }
+
+    /// Returns the client, failing if the command does not declare that it requires authentication.
+    pub fn client(&self) -> command::Result<&Client> {
+        self.client.as_ref().ok_or_else(|| {
+            command::Error::Invalid(String::from(
+                "The command requires authentication, but no client has been created",
+            ))
+        })
+    }
+}
+
+/// A command of the CLI, which prints its result and returns the exit code.
+pub trait RunnableCommand {
+    /// Whether the command needs a client logged in from the env vars
+    fn requires_auth(&self) -> bool {
+        true
+    }
+
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode>;
+
+    /// Prints the requests the command would send with `--dry-run` instead of sending them.
+    /// Commands without a preview run as usual, they either only read or review their
+    /// planned changes themselves.
+    fn preview<'a>(&'a self, _ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        None
+    }
+}
+
+/// Runs the command, or only previews it if `--dry-run` is given.
+pub async fn dispatch(command: &impl RunnableCommand, ctx: &Context<'_>) -> ExitCode {
+    let preview = match ctx.cli.dry_run {
+        true => command.preview(ctx),
+        false => None,
+    };
+    match preview {
+        Some(preview) => preview.await,
+        None => command.run(ctx).await,
+    }
+}
+
+// Boxes the future of a command, logging the error if it fails before running, e.g. without a client.
+fn attempt<'a>(
+    command: impl Future<Output = command::Result<ExitCode>> + 'a,
+) -> LocalBoxFuture<'a, ExitCode> {
+    Box::pin(async move { command.await.unwrap_or_else(fail) })
+}
+
+impl RunnableCommand for Command {
+    fn requires_auth(&self) -> bool {
+        match self {
+            Command::Account(account) => account.command.requires_auth(),
+            Command::Domain(domain) => domain.command.requires_auth(),
+            Command::Email(email) => email.command.requires_auth(),
+            Command::ResourceRecordSet(rrset) => rrset.command.requires_auth(),
+            Command::Repo(repo) => repo.command.requires_auth(),
+            Command::Ptr(_) => true,
+            Command::Tui => true,
+            Command::Token(token) => token.command.requires_auth(),
+            Command::TokenPolicy(policy) => policy.command.requires_auth(),
+        }
+    }
+
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        match self {
+            Command::Account(account) => account.command.run(ctx),
+            Command::Domain(domain) => domain.command.run(ctx),
+            Command::Email(email) => email.command.run(ctx),
+            Command::ResourceRecordSet(rrset) => rrset.command.run(ctx),
+            Command::Repo(repo) => repo.command.run(ctx),
+            Command::Ptr(ptr) => ptr.command.run(ctx),
+            Command::Tui => attempt(async move { Ok(tui(ctx.cli, ctx.client()?).await) }),
+            Command::Token(token) => token.command.run(ctx),
+            Command::TokenPolicy(policy) => policy.command.run(ctx),
+        }
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            Command::Account(account) => account.command.preview(ctx),
+            Command::Domain(domain) => domain.command.preview(ctx),
+            Command::Email(email) => email.command.preview(ctx),
+            Command::ResourceRecordSet(rrset) => rrset.command.preview(ctx),
+            Command::Repo(repo) => repo.command.preview(ctx),
+            Command::Ptr(ptr) => ptr.command.preview(ctx),
+            Command::Tui => None,
+            Command::Token(token) => token.command.preview(ctx),
+            Command::TokenPolicy(policy) => policy.command.preview(ctx),
+        }
+    }
+}
+
+impl RunnableCommand for AccountCommand {
+    fn requires_auth(&self) -> bool {
+        matches!(
+            self,
+            AccountCommand::Show | AccountCommand::UpdateOutreachPreference(_)
+        )
+    }
+
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        attempt(async move {
+            Ok(match self {
+                AccountCommand::Captcha => get_captcha().await,
+                AccountCommand::Register(args) => register(args).await,
+                AccountCommand::Login(args) => login(args).await,
+                AccountCommand::RequestPasswordReset(args) => request_password_reset(args).await,
+                AccountCommand::ConfirmPasswordReset(args) => confirm_password_reset(args).await,
+                AccountCommand::UpdateOutreachPreference(args) => {
+                    update_outreach_preference(ctx.client()?, args).await
+                }
+                AccountCommand::Show => show_account(ctx.client()?).await,
+            })
+        })
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            AccountCommand::Register(args) => {
+                Some(attempt(async move { Ok(dry_run::register(args)) }))
+            }
+            AccountCommand::Login(args) => Some(attempt(async move { Ok(dry_run::login(args)) })),
+            AccountCommand::RequestPasswordReset(args) => {
+                Some(attempt(
+                    async move { Ok(dry_run::request_password_reset(args)) },
+                ))
+            }
+            AccountCommand::ConfirmPasswordReset(args) => {
+                Some(attempt(
+                    async move { Ok(dry_run::confirm_password_reset(args)) },
+                ))
+            }
+            AccountCommand::UpdateOutreachPreference(args) => Some(attempt(async move {
+                Ok(dry_run::update_outreach_preference(ctx.client()?, args).await)
+            })),
+            AccountCommand::Captcha | AccountCommand::Show => None,
+        }
+    }
+}
+
+impl RunnableCommand for DomainCommand {
+    fn requires_auth(&self) -> bool {
+        // Clone logs in with the clients of its profiles
+        match self {
+            DomainCommand::Clone(_) | DomainCommand::ListTemplates => false,
+            DomainCommand::Lint(args) => !args.is_file(),
+            _ => true,
+        }
+    }
+
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            Ok(match self {
+                DomainCommand::List => list_domains(cli, ctx.client()?).await,
+                DomainCommand::Get(args) => get_domain(ctx.client()?, args).await,
+                DomainCommand::Create(args) => create_domain(ctx.client()?, args).await,
+                DomainCommand::Delete(args) => delete_domain(cli, ctx.client()?, args).await,
+                DomainCommand::Responsible(args) => {
+                    get_domain_responsible(ctx.client()?, args).await
+                }
+                DomainCommand::Export(args) => export_domain(ctx.client()?, args).await,
+                DomainCommand::Edit(args) => edit_domain(cli, ctx.client()?, args).await,
+                DomainCommand::Clone(args) => clone_domain(cli, args).await,
+                DomainCommand::ApplyTemplate(args) => {
+                    apply_template(cli, ctx.client()?, args).await
+                }
+                DomainCommand::ListTemplates => list_templates(),
+                DomainCommand::FcrdnsReport(args) => fcrdns_report(ctx.client()?, args).await,
+                DomainCommand::Lint(args) if args.is_file() => lint_zone_file(cli, args),
+                DomainCommand::Lint(args) => lint_domain(cli, ctx.client()?, args).await,
+            })
+        })
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            DomainCommand::Create(args) => Some(attempt(async move {
+                Ok(dry_run::create_domain(ctx.client()?, args).await)
+            })),
+            DomainCommand::Delete(args) => Some(attempt(async move {
+                check_protected(args)?;
+                Ok(dry_run::delete_domain(ctx.client()?, args).await)
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl RunnableCommand for EmailCommand {
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            let client = ctx.client()?;
+            Ok(match self {
+                EmailCommand::Report(args) => email_report(client, args).await,
+                EmailCommand::Spf(args) => email_spf(cli, client, args).await,
+                EmailCommand::Dkim(args) => email_dkim(cli, client, args).await,
+                EmailCommand::Dmarc(args) => email_dmarc(cli, client, args).await,
+                EmailCommand::MtaSts(args) => email_mta_sts(cli, client, args).await,
+            })
+        })
+    }
+}
+
+impl RunnableCommand for ResourceRecordSetCommand {
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            let client = ctx.client()?;
+            Ok(match self {
+                ResourceRecordSetCommand::List(args) => get_all_rrsets(cli, client, args).await,
+                ResourceRecordSetCommand::Get(args) => get_rrset(client, args).await,
+                ResourceRecordSetCommand::Create(args) => create_rrset(cli, client, args).await,
+                ResourceRecordSetCommand::Delete(args) => delete_rrset(cli, client, args).await,
+                ResourceRecordSetCommand::Search(args) => search_rrsets(cli, client, args).await,
+                ResourceRecordSetCommand::Replace(args) => replace_rrsets(cli, client, args).await,
+                ResourceRecordSetCommand::Restore(args) => restore_rrsets(cli, client, args).await,
+                ResourceRecordSetCommand::Migrate(args) => migrate_rrset(cli, client, args).await,
+            })
+        })
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            ResourceRecordSetCommand::Create(args) => Some(attempt(async move {
+                Ok(dry_run::create_rrset(ctx.client()?, args).await)
+            })),
+            ResourceRecordSetCommand::Delete(args) => Some(attempt(async move {
+                Ok(dry_run::delete_rrset(ctx.client()?, args).await)
+            })),
+            ResourceRecordSetCommand::Migrate(args) => Some(attempt(async move {
+                let (_, _, migration) = load_migration(ctx.client()?, args).await?;
+                Ok(dry_run::migrate_rrset(&migration))
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl RunnableCommand for RepoCommand {
+    fn requires_auth(&self) -> bool {
+        !matches!(self, RepoCommand::Init(_))
+    }
+
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            Ok(match self {
+                RepoCommand::Init(args) => repo_init(args),
+                RepoCommand::Pull(args) => repo_pull(ctx.client()?, args).await,
+                RepoCommand::Push(args) => repo_push(cli, ctx.client()?, args).await,
+            })
+        })
+    }
+}
+
+impl RunnableCommand for PtrCommand {
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            let client = ctx.client()?;
+            Ok(match self {
+                PtrCommand::Set(args) => ptr_set(cli, client, args).await,
+                PtrCommand::Sync(args) => ptr_sync(cli, client, args).await,
+            })
+        })
+    }
+}
+
+impl RunnableCommand for TokenCommand {
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            let client = ctx.client()?;
+            Ok(match self {
+                TokenCommand::List => list_token(cli, client).await,
+                TokenCommand::Get(args) => get_token(client, args).await,
+                TokenCommand::Create(args) => create_token(client, args).await,
+                TokenCommand::Delete(args) => delete_token(client, args).await,
+                TokenCommand::Patch(args) => patch_token(client, args).await,
+            })
+        })
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            TokenCommand::Create(args) => {
+                Some(attempt(async move { Ok(dry_run::create_token(args)) }))
+            }
+            TokenCommand::Delete(args) => Some(attempt(async move {
+                Ok(dry_run::delete_token(ctx.client()?, args).await)
+            })),
+            TokenCommand::Patch(args) => Some(attempt(async move {
+                let fields = args.patch().fields();
+                Ok(dry_run::patch_token(ctx.client()?, &args.token_id, &fields).await)
+            })),
+            TokenCommand::List | TokenCommand::Get(_) => None,
+        }
+    }
+}
+
+impl RunnableCommand for TokenPolicyCommand {
+    fn run<'a>(&'a self, ctx: &'a Context) -> LocalBoxFuture<'a, ExitCode> {
+        let cli = ctx.cli;
+        attempt(async move {
+            let client = ctx.client()?;
+            Ok(match self {
+                TokenPolicyCommand::List(args) => list_token_policies(cli, client, args).await,
+                TokenPolicyCommand::Create(args) => create_token_policy(client, args).await,
+                TokenPolicyCommand::Get(args) => get_token_policy(client, args).await,
+                TokenPolicyCommand::Patch(args) => patch_token_policy(client, args).await,
+                TokenPolicyCommand::Delete(args) => delete_token_policy(client, args).await,
+            })
+        })
+    }
+
+    fn preview<'a>(&'a self, ctx: &'a Context) -> Option<LocalBoxFuture<'a, ExitCode>> {
+        match self {
+            TokenPolicyCommand::Create(args) => Some(attempt(async move {
+                Ok(dry_run::create_token_policy(ctx.client()?, args).await)
+            })),
+            TokenPolicyCommand::Patch(args) => Some(attempt(async move {
+                let plan = plan_policy_patch(ctx.client()?, args).await?;
+                Ok(dry_run::patch_token_policy(
+                    args,
+                    &plan.current,
+                    &plan.fields,
+                ))
+            })),
+            TokenPolicyCommand::Delete(args) => Some(attempt(async move {
+                Ok(dry_run::delete_token_policy(ctx.client()?, args).await)
+            })),
+            TokenPolicyCommand::List(_) | TokenPolicyCommand::Get(_) => None,
+        }
+    }
+}
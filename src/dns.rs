@@ -0,0 +1,99 @@
+use desec_api::USERAGENT;
+use reqwest::Url;
+use serde::Deserialize;
+
+/// Resolver used for names the deSEC API cannot answer, e.g. SPF includes of other providers,
+/// unless another one is given with `--resolver` or in the config file.
+pub const DEFAULT_RESOLVER: &str = "https://cloudflare-dns.com/dns-query";
+
+/// A minimal DNS over HTTPS resolver using the JSON API of public resolvers.
+pub struct Resolver {
+    client: reqwest::Client,
+    url: Url,
+}
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    r#type: u16,
+    data: String,
+}
+
+impl Resolver {
+    /// Creates a resolver sending its queries to the given DNS over HTTPS URL.
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(USERAGENT)
+            .build()
+            .map_err(|error| format!("Failed to create the DNS client: {error}"))?;
+        let url =
+            Url::parse(url).map_err(|error| format!("Invalid resolver URL {url}: {error}"))?;
+        Ok(Resolver { client, url })
+    }
+
+    /// Returns the DNS over HTTPS URL the queries are sent to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the data of all records of the given type, empty if the name does not exist.
+    pub async fn lookup(&self, name: &str, record_type: &str) -> Result<Vec<String>, String> {
+        let code = type_code(record_type)
+            .ok_or_else(|| format!("Unsupported record type {record_type}"))?;
+        let response = self
+            .client
+            .get(self.query_url(name, record_type))
+            .header("Accept", "application/dns-json")
+            .send()
+            .await
+            .map_err(|error| format!("Lookup of {record_type} {name} failed: {error}"))?;
+        let text = response
+            .text()
+            .await
+            .map_err(|error| format!("Lookup of {record_type} {name} failed: {error}"))?;
+        let response: DohResponse = serde_json::from_str(&text)
+            .map_err(|error| format!("Invalid response for {record_type} {name}: {error}"))?;
+        match response.status {
+            // NOERROR and NXDOMAIN
+            0 | 3 => Ok(response
+                .answer
+                .into_iter()
+                .filter(|answer| answer.r#type == code)
+                .map(|answer| answer.data)
+                .collect()),
+            status => Err(format!(
+                "Lookup of {record_type} {name} failed with rcode {status}"
+            )),
+        }
+    }
+
+    // The URL of the query with encoded parameters, keeping those of the resolver URL.
+    fn query_url(&self, name: &str, record_type: &str) -> Url {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("name", name)
+            .append_pair("type", record_type);
+        url
+    }
+}
+
+fn type_code(r#type: &str) -> Option<u16> {
+    match r#type {
+        "A" => Some(1),
+        "NS" => Some(2),
+        "CNAME" => Some(5),
+        "PTR" => Some(12),
+        "MX" => Some(15),
+        "TXT" => Some(16),
+        "AAAA" => Some(28),
+        _ => None,
+    }
+}
@@ -0,0 +1,473 @@
+use crate::cli::*;
+use desec_api::{Error, API_URL};
+use desec_cli::api::token::{PolicyFields, TokenFields};
+use desec_cli::api::Client;
+use desec_cli::changeset::RrsetState;
+use desec_cli::migrate::{Migration, Step};
+use desec_cli::rrset_name;
+use log::error;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::process::ExitCode;
+
+/// A request which a mutating command would send, printed instead of being sent.
+pub struct Preview {
+    method: &'static str,
+    endpoint: String,
+    body: Option<Value>,
+    effects: Vec<String>,
+}
+
+impl Preview {
+    pub fn new(method: &'static str, endpoint: impl Into<String>) -> Self {
+        Preview {
+            method,
+            endpoint: endpoint.into(),
+            body: None,
+            effects: vec![],
+        }
+    }
+
+    pub fn body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn effect(mut self, effect: impl Into<String>) -> Self {
+        self.effects.push(effect.into());
+        self
+    }
+
+    /// Prints the request and its effects and reports success, as nothing has been sent.
+    pub fn print(&self) -> ExitCode {
+        println!("{} {}{}", self.method, API_URL, self.endpoint);
+        if let Some(body) = &self.body {
+            match serde_json::to_string_pretty(body) {
+                Ok(json) => println!("{json}"),
+                Err(error) => {
+                    error!("Failed to serialize the request body: {error}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        println!();
+        for effect in &self.effects {
+            println!("{effect}");
+        }
+        println!("Dry run: the request has not been sent");
+        ExitCode::SUCCESS
+    }
+}
+
+/// The bulk request writing the new states of the rrsets of the domain.
+pub fn rrset_changes(domain: &str, rrsets: &[RrsetState]) -> Preview {
+    Preview::new("PATCH", format!("/domains/{domain}/rrsets/"))
+        .body(json!(rrsets))
+        .effect(format!("Would change {} rrsets of {domain}", rrsets.len()))
+}
+
+pub fn register(args: &RegisterArgs) -> ExitCode {
+    let mut body = json!({
+        "email": args.email,
+        "password": "***",
+        "captcha": {
+            "id": args.id,
+            "solution": args.solution
+        }
+    });
+    let mut preview = Preview::new("POST", "/auth/");
+    if let Some(domain) = &args.domain {
+        body["domain"] = Value::String(domain.clone());
+        preview = preview.effect(format!(
+            "Would register the account {} together with the domain {domain}",
+            args.email
+        ));
+    } else {
+        preview = preview.effect(format!("Would register the account {}", args.email));
+    }
+    preview.body(body).print()
+}
+
+pub fn login(args: &LoginArgs) -> ExitCode {
+    Preview::new("POST", "/auth/login/")
+        .body(json!({"email": args.email, "password": "***"}))
+        .effect(format!(
+            "Would create a new login token for the account {}",
+            args.email
+        ))
+        .print()
+}
+
+pub fn request_password_reset(args: &RequestPasswordResetArgs) -> ExitCode {
+    Preview::new("POST", "/auth/account/reset-password/")
+        .body(json!({
+            "email": args.email,
+            "captcha": {
+                "id": args.captcha_id,
+                "solution": args.captcha_solution
+            }
+        }))
+        .effect(format!(
+            "Would send a password reset email to {} if such an account exists",
+            args.email
+        ))
+        .print()
+}
+
+pub fn confirm_password_reset(args: &ConfirmPasswordResetArgs) -> ExitCode {
+    Preview::new(
+        "POST",
+        format!("/auth/account/reset-password/{}", args.code),
+    )
+    .body(json!({"new_password": "***"}))
+    .effect("Would set the password of the account to the given password")
+    .print()
+}
+
+pub async fn update_outreach_preference(
+    client: &Client,
+    args: &UpdateOutreachPreferenceArgs,
+) -> ExitCode {
+    let preview = Preview::new("PATCH", "/auth/account/")
+        .body(json!({"outreach_preference": args.outreach_preference}));
+    match client.account().get_account_info().await {
+        Ok(info) if info.outreach_preference == args.outreach_preference => {
+            preview.effect(format!(
+                "The outreach preference already is {}, nothing would change",
+                info.outreach_preference
+            ))
+        }
+        Ok(info) => preview.effect(format!(
+            "Would change the outreach preference from {} to {}",
+            info.outreach_preference, args.outreach_preference
+        )),
+        Err(error) => unknown_state(preview, error).effect(format!(
+            "Would set the outreach preference to {}",
+            args.outreach_preference
+        )),
+    }
+    .print()
+}
+
+pub async fn create_domain(client: &Client, args: &DomainNameArg) -> ExitCode {
+    let preview = Preview::new("POST", "/domains/").body(json!({"name": args.name}));
+    match client.domain().get_domain(&args.name).await {
+        Ok(_) => preview.effect(format!(
+            "The domain {} already exists in your account, the request would fail",
+            args.name
+        )),
+        Err(Error::NotFound) => preview.effect(format!("Would create the domain {}", args.name)),
+        Err(error) => {
+            unknown_state(preview, error).effect(format!("Would create the domain {}", args.name))
+        }
+    }
+    .print()
+}
+
+pub async fn delete_domain(client: &Client, args: &DomainDeleteArgs) -> ExitCode {
+    let preview = Preview::new("DELETE", format!("/domains/{}/", args.name));
+    match client.rrset().get_rrsets(&args.name).await {
+        Ok(rrsets) => preview.effect(format!(
+            "Would delete the domain {} including its {} rrsets",
+            args.name,
+            rrsets.len()
+        )),
+        Err(Error::NotFound) => preview.effect(format!(
+            "The domain {} does not exist or you are not the owner, nothing would change",
+            args.name
+        )),
+        Err(error) => {
+            unknown_state(preview, error).effect(format!("Would delete the domain {}", args.name))
+        }
+    }
+    .print()
+}
+
+pub async fn create_rrset(client: &Client, args: &ResourceRecordSetCreateArgs) -> ExitCode {
+    let (name, ttl, records) = match rrset_name::resolve_with_records(client, &args.rrset).await {
+        Ok(resolved) => resolved,
+        Err(error) => {
+            error!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let preview = Preview::new("POST", format!("/domains/{}/rrsets/", name.domain)).body(json!({
+        "subname": name.subname,
+        "type": name.r#type,
+        "ttl": ttl,
+        "records": records
+    }));
+    match client
+        .rrset()
+        .get_rrset(&name.domain, name.subname(), &name.r#type)
+        .await
+    {
+        Ok(existing) => preview.effect(format!(
+            "The rrset {name} already exists with records {:?}, the request would fail",
+            existing.records
+        )),
+        Err(Error::NotFound) => preview.effect(format!(
+            "Would create the rrset {name} with TTL {ttl} and records {records:?}"
+        )),
+        Err(error) => unknown_state(preview, error).effect(format!(
+            "Would create the rrset {name} with TTL {ttl} and records {records:?}"
+        )),
+    }
+    .print()
+}
+
+pub async fn delete_rrset(client: &Client, args: &ResourceRecordSetDeleteArgs) -> ExitCode {
+    let name = match rrset_name::resolve(client, &args.rrset).await {
+        Ok(name) => name,
+        Err(error) => {
+            error!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let preview = Preview::new(
+        "DELETE",
+        format!(
+            "/domains/{}/rrsets/{}/{}/",
+            name.domain,
+            name.url_subname(),
+            name.r#type
+        ),
+    );
+    match client
+        .rrset()
+        .get_rrset(&name.domain, name.subname(), &name.r#type)
+        .await
+    {
+        Ok(existing) => preview.effect(format!(
+            "Would delete the rrset {name} with TTL {} and records {:?}",
+            existing.ttl, existing.records
+        )),
+        Err(Error::NotFound) => preview.effect(format!(
+            "The rrset {name} does not exist, nothing would change"
+        )),
+        Err(error) => {
+            unknown_state(preview, error).effect(format!("Would delete the rrset {name}"))
+        }
+    }
+    .print()
+}
+
+pub fn create_token(args: &TokenCreateArgs) -> ExitCode {
+    let mut body = Map::new();
+    if let Some(name) = &args.name {
+        body.insert("name".to_string(), json!(name));
+    }
+    if let Some(subnets) = &args.subnets {
+        body.insert("allowed_subnets".to_string(), json!(subnets));
+    }
+    if let Some(manage) = args.manage {
+        body.insert("perm_manage_tokens".to_string(), json!(manage));
+    }
+    if let Some(max_age) = &args.max_age {
+        body.insert("max_age".to_string(), json!(max_age));
+    }
+    if let Some(max_unused_period) = &args.max_unused_period {
+        body.insert("max_unused_period".to_string(), json!(max_unused_period));
+    }
+    Preview::new("POST", "/auth/tokens/")
+        .body(Value::Object(body))
+        .effect("Would create a new token, the secret is only shown once on creation")
+        .print()
+}
+
+pub async fn patch_token(client: &Client, token_id: &str, fields: &TokenFields) -> ExitCode {
+    let payload = fields.body();
+    let preview = Preview::new("PATCH", format!("/auth/tokens/{token_id}/")).body(payload.clone());
+    match client.token().get(token_id).await {
+        Ok(token) => changes(preview, &token, &payload),
+        Err(error) => unknown_state(preview, error),
+    }
+    .print()
+}
+
+pub async fn delete_token(client: &Client, args: &TokenIdArgs) -> ExitCode {
+    let preview = Preview::new("DELETE", format!("/auth/tokens/{}/", args.token_id));
+    match client.token().get(&args.token_id).await {
+        Ok(token) => preview.effect(format!(
+            "Would delete the token {} named '{}', which was last used {}",
+            token.id,
+            token.name,
+            token.last_used.as_deref().unwrap_or("never")
+        )),
+        Err(Error::NotFound) => preview.effect(format!(
+            "The token {} does not exist, the request would fail",
+            args.token_id
+        )),
+        Err(error) => unknown_state(preview, error)
+            .effect(format!("Would delete the token {}", args.token_id)),
+    }
+    .print()
+}
+
+pub async fn create_token_policy(client: &Client, args: &TokenPolicyCreateArgs) -> ExitCode {
+    let domain = args.domain.clone().filter(|d| !d.is_empty());
+    let subname = args.subname.clone().filter(|s| !s.is_empty());
+    let r#type = args.r#type.clone().filter(|r| !r.is_empty());
+    let is_default = domain.is_none() && subname.is_none() && r#type.is_none();
+    let scope = policy_scope(&domain, &subname, &r#type);
+    let preview = Preview::new(
+        "POST",
+        format!("/auth/tokens/{}/policies/rrsets/", args.token_id),
+    )
+    .body(json!({
+        "domain": domain,
+        "subname": subname,
+        "type": r#type,
+        "perm_write": args.perm_write.unwrap_or_default()
+    }));
+    let access = if args.perm_write.unwrap_or_default() {
+        "write"
+    } else {
+        "read-only"
+    };
+    match client.token().list_policies(&args.token_id).await {
+        Ok(policies) if policies.is_empty() && !is_default => preview.effect(format!(
+            "The token {} has no default policy yet, the request would fail",
+            args.token_id
+        )),
+        Ok(_) => preview.effect(format!(
+            "Would grant the token {} {access} access to {scope}",
+            args.token_id
+        )),
+        Err(error) => unknown_state(preview, error).effect(format!(
+            "Would grant the token {} {access} access to {scope}",
+            args.token_id
+        )),
+    }
+    .print()
+}
+
+pub fn patch_token_policy(
+    args: &TokenPolicyPatchArgs,
+    current: &PolicyFields,
+    fields: &PolicyFields,
+) -> ExitCode {
+    let payload = fields.body();
+    let preview = Preview::new(
+        "PATCH",
+        format!(
+            "/auth/tokens/{}/policies/rrsets/{}/",
+            args.token_id, args.policy_id
+        ),
+    )
+    .body(payload.clone());
+    changes(preview, &current.body(), &payload).print()
+}
+
+pub async fn delete_token_policy(client: &Client, args: &TokenPolicyDeleteArgs) -> ExitCode {
+    let preview = Preview::new(
+        "DELETE",
+        format!(
+            "/auth/tokens/{}/policies/rrsets/{}/",
+            args.token_id, args.policy_id
+        ),
+    );
+    match client
+        .token()
+        .get_policy(&args.token_id, &args.policy_id)
+        .await
+    {
+        Ok(policy) => preview.effect(format!(
+            "Would delete the policy {} granting {} access to {}",
+            policy.id,
+            if policy.perm_write {
+                "write"
+            } else {
+                "read-only"
+            },
+            policy_scope(&policy.domain, &policy.subname, &policy.r#type)
+        )),
+        Err(Error::NotFound) => preview.effect(format!(
+            "The policy {} does not exist, the request would fail",
+            args.policy_id
+        )),
+        Err(error) => unknown_state(preview, error)
+            .effect(format!("Would delete the policy {}", args.policy_id)),
+    }
+    .print()
+}
+
+// Notes that the effect cannot be described exactly because the current state is unknown.
+fn unknown_state(preview: Preview, error: Error) -> Preview {
+    preview.effect(format!("Could not fetch the current state: {error}"))
+}
+
+// Describes every field of the payload which differs from the current object.
+fn changes<T: Serialize>(preview: Preview, current: &T, payload: &Value) -> Preview {
+    let current = match serde_json::to_value(current) {
+        Ok(current) => current,
+        Err(error) => {
+            return preview.effect(format!("Failed to serialize the current state: {error}"))
+        }
+    };
+    let mut preview = preview;
+    let mut changed = false;
+    for (field, new) in payload.as_object().into_iter().flatten() {
+        let old = current.get(field).unwrap_or(&Value::Null);
+        if old != new {
+            preview = preview.effect(format!("Would change {field} from {old} to {new}"));
+            changed = true;
+        }
+    }
+    if !changed {
+        preview = preview.effect("All given values match the current state, nothing would change");
+    }
+    preview
+}
+
+fn policy_scope(
+    domain: &Option<String>,
+    subname: &Option<String>,
+    r#type: &Option<String>,
+) -> String {
+    if domain.is_none() && subname.is_none() && r#type.is_none() {
+        return String::from("everything not covered by other policies (default policy)");
+    }
+    let field = |value: &Option<String>| value.clone().unwrap_or_else(|| String::from("*"));
+    format!(
+        "domain {} subname {} type {}",
+        field(domain),
+        field(subname),
+        field(r#type)
+    )
+}
+
+pub fn migrate_rrset(migration: &Migration) -> ExitCode {
+    let mut migration = migration.clone();
+    while let Some(change) = migration.next_change() {
+        let effect = match migration.step {
+            Step::Planned if migration.lowered_ttl < migration.original.ttl => format!(
+                "Would lower the TTL from {} to {} and wait {} seconds",
+                migration.original.ttl, migration.lowered_ttl, migration.original.ttl
+            ),
+            Step::Planned => format!(
+                "Would keep the TTL of {}, it is the minimum of the domain already",
+                migration.original.ttl
+            ),
+            Step::Lowered => format!(
+                "Would switch the records to {}",
+                migration.records.join(", ")
+            ),
+            _ if migration.wait() > 0 => format!(
+                "Would wait {} seconds and restore the TTL to {}",
+                migration.wait(),
+                migration.original.ttl
+            ),
+            _ => format!("Would restore the TTL to {}", migration.original.ttl),
+        };
+        let preview = Preview::new("PATCH", format!("/domains/{}/rrsets/", change.domain))
+            .body(json!([change.after]))
+            .effect(effect);
+        if preview.print() == ExitCode::FAILURE {
+            return ExitCode::FAILURE;
+        }
+        println!();
+        migration.advance();
+    }
+    ExitCode::SUCCESS
+}
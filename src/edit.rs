@@ -0,0 +1,260 @@
+//! Files for editing the rrsets of a domain in an editor, as zone file or YAML.
+
+use crate::changeset::RrsetState;
+use crate::{rrset_name, zone};
+use desec_api::rrset::ResourceRecordSet;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+/// The format of the file opened in the editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Zone,
+    Yaml,
+}
+
+impl Format {
+    /// The file extension, so editors pick the right syntax highlighting.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zone => "zone",
+            Format::Yaml => "yaml",
+        }
+    }
+
+    fn comment(self) -> &'static str {
+        match self {
+            Format::Zone => ";",
+            Format::Yaml => "#",
+        }
+    }
+}
+
+/// Renders the rrsets of the domain for editing, with a header explaining the file.
+/// DNSSEC records are left out, they are managed by deSEC.
+pub fn render(
+    domain: &str,
+    rrsets: &[ResourceRecordSet],
+    format: Format,
+) -> Result<String, String> {
+    let body = match format {
+        Format::Zone => zone::render(domain, rrsets, &[], Default::default()),
+        Format::Yaml => {
+            let states: Vec<RrsetState> = rrsets
+                .iter()
+                .filter(|rrset| !zone::DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+                .map(RrsetState::from)
+                .collect();
+            serde_yaml::to_string(&states)
+                .map_err(|error| format!("Failed to render the rrsets: {error}"))?
+        }
+    };
+    let comment = format.comment();
+    Ok(format!(
+        "{comment} Edit the rrsets of {domain}, removed rrsets are deleted.\n\
+         {comment} The changes are shown for confirmation after saving, an empty file cancels the edit.\n\
+         {body}"
+    ))
+}
+
+/// Parses the edited file into the desired rrsets of the domain.
+pub fn parse(domain: &str, text: &str, format: Format) -> Result<Vec<RrsetState>, String> {
+    match format {
+        Format::Zone => zone::parse(domain, text),
+        Format::Yaml => {
+            let rrsets: Option<Vec<RrsetState>> =
+                serde_yaml::from_str(text).map_err(|error| error.to_string())?;
+            Ok(rrsets
+                .unwrap_or_default()
+                .into_iter()
+                .map(|rrset| RrsetState {
+                    subname: rrset_name::subname(&rrset.subname)
+                        .unwrap_or_default()
+                        .to_string(),
+                    r#type: rrset.r#type.to_ascii_uppercase(),
+                    ..rrset
+                })
+                .collect())
+        }
+    }
+}
+
+/// Whether the file holds nothing but comments, which cancels the edit.
+pub fn is_empty(text: &str, format: Format) -> bool {
+    text.lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || line.starts_with(format.comment()))
+}
+
+/// Puts the error as comment at the top of the file to reopen it in the editor,
+/// replacing the error of a previous attempt.
+pub fn annotate(text: &str, error: &str, format: Format) -> String {
+    let marker = format!("{} Error: ", format.comment());
+    let rest: Vec<&str> = text
+        .lines()
+        .skip_while(|line| line.starts_with(&marker))
+        .collect();
+    let error: Vec<String> = error
+        .lines()
+        .map(|line| format!("{marker}{line}"))
+        .collect();
+    format!("{}\n{}\n", error.join("\n"), rest.join("\n"))
+}
+
+/// The result of editing the file.
+pub enum Edited {
+    /// The desired rrsets of the domain
+    Rrsets(Vec<RrsetState>),
+    /// The edit has been cancelled, with the reason
+    Cancelled(&'static str),
+}
+
+/// Creates a new file with the text in the temporary directory, readable only by the user.
+///
+/// The file must not exist yet, so a file or symlink planted at the path by someone else
+/// is never written to. Another name is tried in that case.
+pub fn create_file(domain: &str, text: &str, format: Format) -> Result<PathBuf, String> {
+    let mut attempts = 0;
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos());
+        let path = env::temp_dir().join(format!(
+            "desec-{domain}-{}-{nanos}.{}",
+            std::process::id(),
+            format.extension()
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(mut file) => {
+                return file
+                    .write_all(text.as_bytes())
+                    .map(|_| path.clone())
+                    .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+            }
+            Err(error) if error.kind() == ErrorKind::AlreadyExists && attempts < 10 => {
+                attempts += 1
+            }
+            Err(error) => return Err(format!("Failed to create {}: {error}", path.display())),
+        }
+    }
+}
+
+/// Opens the file created by [`create_file`] in the editor until it can be parsed,
+/// annotating it with the parse error.
+pub fn edit_until_valid(
+    path: &Path,
+    domain: &str,
+    text: &mut String,
+    format: Format,
+) -> Result<Edited, String> {
+    loop {
+        open_editor(path)?;
+        let edited = fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+        if is_empty(&edited, format) {
+            return Ok(Edited::Cancelled("the file is empty"));
+        }
+        let error = match parse(domain, &edited, format) {
+            Ok(desired) => return Ok(Edited::Rrsets(desired)),
+            Err(error) => error,
+        };
+        // Saving the annotated file unchanged would reopen it forever
+        if edited == *text {
+            return Ok(Edited::Cancelled("the file has not been changed"));
+        }
+        *text = annotate(&edited, &error, format);
+        fs::write(path, &*text)
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))?;
+    }
+}
+
+// Runs $VISUAL or $EDITOR through the shell, so they can hold arguments like `code --wait`.
+fn open_editor(path: &Path) -> Result<(), String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from("vi"));
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .map_err(|error| format!("Failed to run the editor {editor}: {error}"))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "The editor {editor} failed, nothing has been changed"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotate, create_file, parse, Format};
+
+    #[test]
+    fn annotate_replaces_previous_error() {
+        let text = "; Edit the rrsets\nwww 300 IN A 192.0.2.1\n";
+        let once = annotate(text, "line 2: first\nsecond line", Format::Zone);
+        assert_eq!(
+            once,
+            "; Error: line 2: first\n; Error: second line\n; Edit the rrsets\nwww 300 IN A 192.0.2.1\n"
+        );
+        let twice = annotate(&once, "line 3: other", Format::Zone);
+        assert_eq!(
+            twice,
+            "; Error: line 3: other\n; Edit the rrsets\nwww 300 IN A 192.0.2.1\n"
+        );
+        assert!(annotate("- ttl: 1", "invalid", Format::Yaml).starts_with("# Error: invalid\n"));
+    }
+
+    #[test]
+    fn parse_zone_and_yaml() {
+        let zone = parse("example.com", "www 300 IN a 192.0.2.1", Format::Zone).unwrap();
+        let yaml = parse(
+            "example.com",
+            "# Edit the rrsets\n- subname: www\n  type: a\n  ttl: 300\n  records: [192.0.2.1]\n",
+            Format::Yaml,
+        )
+        .unwrap();
+        assert_eq!(zone, yaml);
+        assert_eq!(zone[0].subname, "www");
+        assert_eq!(zone[0].r#type, "A");
+    }
+
+    #[test]
+    fn parse_yaml_apex_and_empty() {
+        let apex = parse(
+            "example.com",
+            "- subname: \"@\"\n  type: TXT\n  ttl: 3600\n  records: ['\"x\"']\n",
+            Format::Yaml,
+        )
+        .unwrap();
+        assert_eq!(apex[0].subname, "");
+        assert!(parse("example.com", "# only comments\n", Format::Yaml)
+            .unwrap()
+            .is_empty());
+        assert!(parse("example.com", "- subname: www\n", Format::Yaml).is_err());
+    }
+
+    #[test]
+    fn create_file_private() {
+        let path = create_file("example.com", "text", Format::Zone).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "text");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! Forward-confirmed reverse DNS: the PTR record of every address points to a name
+//! whose A or AAAA records contain the address again, as mail servers check it.
+//!
+//! The report is computed from the rrsets of the account only, names and addresses
+//! outside of its zones cannot be checked. Addresses of wildcard names need no PTR record,
+//! but confirm PTR records pointing to names the wildcard covers.
+
+use crate::reverse;
+use desec_api::rrset::ResourceRecordSet;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+#[derive(Serialize, Debug, Default)]
+pub struct Report {
+    /// Addresses of A and AAAA records in a reverse zone of the account without PTR record
+    pub missing_ptr: Vec<Entry>,
+    /// PTR records pointing to names in a zone of the account without A or AAAA records
+    pub missing_forward: Vec<Entry>,
+    /// Addresses whose PTR records and the A and AAAA records of the names do not match
+    pub mismatches: Vec<Mismatch>,
+    /// Addresses of A and AAAA records outside of the reverse zones of the account
+    pub unchecked: Vec<Entry>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Entry {
+    pub address: IpAddr,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mismatch {
+    pub address: IpAddr,
+    pub name: String,
+    /// The names the PTR records of the address point to
+    pub ptr: Vec<String>,
+    /// The addresses of the A and AAAA records of the name
+    pub addresses: Vec<IpAddr>,
+}
+
+/// Cross-references the A and AAAA records of the domains with the PTR records of the reverse zones.
+pub fn report(domains: &[(String, Vec<ResourceRecordSet>)]) -> Report {
+    let reverse_zones: Vec<String> = domains
+        .iter()
+        .map(|(domain, _)| reverse::fqdn(domain))
+        .filter(|domain| reverse::is_reverse_zone(domain))
+        .collect();
+    let forward_zones: Vec<String> = domains
+        .iter()
+        .map(|(domain, _)| reverse::fqdn(domain))
+        .filter(|domain| !reverse::is_reverse_zone(domain))
+        .collect();
+    // Addresses of every name and names of every address, all names fully qualified
+    let mut forward: BTreeMap<String, BTreeSet<IpAddr>> = BTreeMap::new();
+    // Addresses of wildcard names by the name the wildcard is at, e.g. example.com. for *.example.com.
+    let mut wildcards: BTreeMap<String, BTreeSet<IpAddr>> = BTreeMap::new();
+    let mut ptr: BTreeMap<IpAddr, BTreeSet<String>> = BTreeMap::new();
+    for (domain, rrsets) in domains {
+        for rrset in rrsets {
+            let name = match rrset.subname.as_deref().unwrap_or_default() {
+                "" => reverse::fqdn(domain),
+                subname => reverse::fqdn(&format!("{subname}.{domain}")),
+            };
+            match rrset.rrset_type.as_str() {
+                "A" | "AAAA" => match name.strip_prefix("*.") {
+                    Some(parent) => wildcards.entry(parent.to_string()).or_default(),
+                    None => forward.entry(name).or_default(),
+                }
+                .extend(
+                    rrset
+                        .records
+                        .iter()
+                        .filter_map(|record| record.parse::<IpAddr>().ok()),
+                ),
+                "PTR" => {
+                    if let Some(address) = reverse::address(&name) {
+                        ptr.entry(address)
+                            .or_default()
+                            .extend(rrset.records.iter().map(|record| reverse::fqdn(record)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    // The addresses of a name, of its own records or the closest wildcard covering it
+    let resolve = |name: &str| {
+        forward.get(name).or_else(|| {
+            let mut parent = name;
+            while let Some((_, rest)) = parent.split_once('.') {
+                if let Some(addresses) = wildcards.get(rest) {
+                    return Some(addresses);
+                }
+                parent = rest;
+            }
+            None
+        })
+    };
+    let mut report = Report::default();
+    let mut mismatches = BTreeSet::new();
+    let mismatch = |address: IpAddr, name: &str| Mismatch {
+        address,
+        name: name.to_string(),
+        ptr: ptr.get(&address).into_iter().flatten().cloned().collect(),
+        addresses: resolve(name).into_iter().flatten().copied().collect(),
+    };
+    for (name, addresses) in &forward {
+        for &address in addresses {
+            let entry = Entry {
+                address,
+                name: name.clone(),
+            };
+            match ptr.get(&address) {
+                Some(names) if names.contains(name) => {}
+                Some(_) => {
+                    mismatches.insert(mismatch(address, name));
+                }
+                None if reverse::split(
+                    &reverse::fqdn(&reverse::reverse_name(address)),
+                    &reverse_zones,
+                )
+                .is_some() =>
+                {
+                    report.missing_ptr.push(entry)
+                }
+                None => report.unchecked.push(entry),
+            }
+        }
+    }
+    for (&address, names) in &ptr {
+        for name in names {
+            let entry = Entry {
+                address,
+                name: name.clone(),
+            };
+            match resolve(name) {
+                Some(addresses) if addresses.contains(&address) => {}
+                Some(_) => {
+                    mismatches.insert(mismatch(address, name));
+                }
+                None if reverse::split(name, &forward_zones).is_some() => {
+                    report.missing_forward.push(entry)
+                }
+                // Names outside of the zones of the account cannot be checked
+                None => {}
+            }
+        }
+    }
+    report.mismatches = mismatches.into_iter().collect();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rrset(domain: &str, subname: &str, r#type: &str, records: &[&str]) -> ResourceRecordSet {
+        ResourceRecordSet {
+            created: String::new(),
+            domain: domain.to_string(),
+            subname: Some(subname.to_string()),
+            name: String::new(),
+            rrset_type: r#type.to_string(),
+            ttl: 3600,
+            records: records.iter().map(|record| record.to_string()).collect(),
+            touched: String::new(),
+        }
+    }
+
+    fn entry(address: &str, name: &str) -> Entry {
+        Entry {
+            address: address.parse().unwrap(),
+            name: name.to_string(),
+        }
+    }
+
+    fn domains(
+        forward: Vec<ResourceRecordSet>,
+        ptr: Vec<ResourceRecordSet>,
+    ) -> Vec<(String, Vec<ResourceRecordSet>)> {
+        vec![
+            (String::from("example.com"), forward),
+            (String::from("2.0.192.in-addr.arpa"), ptr),
+        ]
+    }
+
+    #[test]
+    fn confirmed() {
+        let report = report(&domains(
+            vec![rrset("example.com", "mail", "A", &["192.0.2.1"])],
+            vec![rrset(
+                "2.0.192.in-addr.arpa",
+                "1",
+                "PTR",
+                &["mail.example.com."],
+            )],
+        ));
+        assert!(report.missing_ptr.is_empty());
+        assert!(report.missing_forward.is_empty());
+        assert!(report.mismatches.is_empty());
+        assert!(report.unchecked.is_empty());
+    }
+
+    #[test]
+    fn missing_and_unchecked() {
+        let report = report(&domains(
+            vec![rrset(
+                "example.com",
+                "www",
+                "A",
+                &["192.0.2.2", "198.51.100.1"],
+            )],
+            vec![rrset(
+                "2.0.192.in-addr.arpa",
+                "3",
+                "PTR",
+                &["mail.example.com."],
+            )],
+        ));
+        assert_eq!(
+            report.missing_ptr,
+            vec![entry("192.0.2.2", "www.example.com.")]
+        );
+        assert_eq!(
+            report.unchecked,
+            vec![entry("198.51.100.1", "www.example.com.")]
+        );
+        assert_eq!(
+            report.missing_forward,
+            vec![entry("192.0.2.3", "mail.example.com.")]
+        );
+    }
+
+    #[test]
+    fn mismatch() {
+        let report = report(&domains(
+            vec![rrset("example.com", "mail", "A", &["192.0.2.1"])],
+            vec![rrset(
+                "2.0.192.in-addr.arpa",
+                "1",
+                "PTR",
+                &["www.example.com."],
+            )],
+        ));
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].name, "mail.example.com.");
+        assert_eq!(report.mismatches[0].ptr, vec!["www.example.com."]);
+        assert!(report
+            .missing_forward
+            .contains(&entry("192.0.2.1", "www.example.com.")));
+    }
+
+    #[test]
+    fn wildcards_need_no_ptr() {
+        let report = report(&domains(
+            vec![
+                rrset("example.com", "*", "A", &["192.0.2.1"]),
+                rrset("example.com", "*.dev", "AAAA", &["2001:db8::1"]),
+            ],
+            vec![],
+        ));
+        assert!(report.missing_ptr.is_empty());
+        assert!(report.unchecked.is_empty());
+    }
+
+    #[test]
+    fn wildcards_confirm_ptr() {
+        let report = report(&domains(
+            vec![
+                rrset("example.com", "*", "A", &["192.0.2.1"]),
+                rrset("example.com", "*.dev", "A", &["192.0.2.2"]),
+            ],
+            vec![
+                rrset("2.0.192.in-addr.arpa", "1", "PTR", &["mail.example.com."]),
+                rrset(
+                    "2.0.192.in-addr.arpa",
+                    "2",
+                    "PTR",
+                    &["api.dev.example.com."],
+                ),
+            ],
+        ));
+        assert!(report.missing_forward.is_empty());
+        assert!(report.mismatches.is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+//! Library behind the desec_cli command line client for the deSEC DNS API.
+//!
+//! The [`command`] module holds the operations of the CLI returning typed results,
+//! so they can be used from other tools and tests, e.g. [`command::rrset::sync`].
+//! The remaining modules are the building blocks of those commands.
+
+pub mod api;
+pub mod changeset;
+pub mod command;
+pub mod config;
+pub mod conflict;
+pub mod dns;
+pub mod edit;
+pub mod email;
+pub mod fcrdns;
+pub mod lint;
+pub mod migrate;
+pub mod repo;
+pub mod reverse;
+pub mod rrset_name;
+pub mod search;
+pub mod template;
+pub mod zone;
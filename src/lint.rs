@@ -0,0 +1,407 @@
+//! Checks of a zone for common mistakes, on the rrsets of the API or a local zone file.
+
+use crate::changeset::RrsetState;
+use crate::rrset_name;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The zone is broken or the API rejects it
+    Error,
+    /// The zone works but misses good practice
+    Warning,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The subname, @ for the zone apex
+    pub subname: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub message: String,
+}
+
+/// Maximum length of a single character string of a TXT record.
+const MAX_TXT_STRING: usize = 255;
+
+/// Checks the rrsets of the domain, sorted by severity and name.
+pub fn lint(domain: &str, rrsets: &[RrsetState], minimum_ttl: u64) -> Vec<Finding> {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let mut findings = vec![];
+    let mut finding = |severity, rrset: &RrsetState, message: String| {
+        findings.push(Finding {
+            severity,
+            subname: rrset_name::display_subname(&rrset.subname).to_string(),
+            r#type: rrset.r#type.clone(),
+            message,
+        })
+    };
+    let names: BTreeSet<&str> = rrsets.iter().map(|rrset| rrset.subname.as_str()).collect();
+    let cnames: BTreeSet<&str> = rrsets
+        .iter()
+        .filter(|rrset| rrset.r#type == "CNAME")
+        .map(|rrset| rrset.subname.as_str())
+        .collect();
+    // Names below a delegation are served by other name servers
+    let delegations: Vec<&str> = rrsets
+        .iter()
+        .filter(|rrset| rrset.r#type == "NS" && !rrset.subname.is_empty())
+        .map(|rrset| rrset.subname.as_str())
+        .collect();
+    let delegated = |subname: &str| {
+        delegations.iter().any(|delegation| {
+            subname == *delegation || subname.ends_with(&format!(".{delegation}"))
+        })
+    };
+
+    for rrset in rrsets {
+        if rrset.ttl < minimum_ttl {
+            finding(
+                Severity::Error,
+                rrset,
+                format!(
+                    "TTL {} is below the minimum TTL {minimum_ttl} of the domain",
+                    rrset.ttl
+                ),
+            );
+        }
+        match rrset.r#type.as_str() {
+            "CNAME" if rrset.subname.is_empty() => finding(
+                Severity::Error,
+                rrset,
+                "CNAME at the zone apex, which needs SOA and NS records".to_string(),
+            ),
+            "CNAME" => {
+                let others: Vec<&str> = rrsets
+                    .iter()
+                    .filter(|other| other.subname == rrset.subname && other.r#type != "CNAME")
+                    .map(|other| other.r#type.as_str())
+                    .collect();
+                if !others.is_empty() {
+                    finding(
+                        Severity::Error,
+                        rrset,
+                        format!("CNAME next to other records: {}", others.join(", ")),
+                    );
+                }
+            }
+            "TXT" => {
+                let spf = rrset
+                    .records
+                    .iter()
+                    .filter(|record| {
+                        txt_strings(record)
+                            .concat()
+                            .to_ascii_lowercase()
+                            .starts_with("v=spf1")
+                    })
+                    .count();
+                if spf > 1 {
+                    finding(
+                        Severity::Error,
+                        rrset,
+                        format!("{spf} SPF records, receivers treat this as an error"),
+                    );
+                }
+                for record in &rrset.records {
+                    if let Some(string) = txt_strings(record)
+                        .iter()
+                        .find(|string| string.len() > MAX_TXT_STRING)
+                    {
+                        finding(
+                            Severity::Error,
+                            rrset,
+                            format!(
+                                "String of {} bytes exceeds {MAX_TXT_STRING} bytes, split it into multiple quoted strings",
+                                string.len()
+                            ),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        for target in targets(rrset) {
+            let Some(subname) = in_zone(&target, &domain) else {
+                continue;
+            };
+            if delegated(&subname) {
+                continue;
+            }
+            if rrset.r#type != "CNAME" && cnames.contains(subname.as_str()) {
+                finding(
+                    Severity::Error,
+                    rrset,
+                    format!(
+                        "Target {target} is a CNAME, which is not allowed for {}",
+                        rrset.r#type
+                    ),
+                );
+            } else if !names.contains(subname.as_str()) && !wildcard(&names, &subname) {
+                finding(
+                    Severity::Error,
+                    rrset,
+                    format!("Target {target} has no records in the zone"),
+                );
+            }
+        }
+    }
+    if !rrsets
+        .iter()
+        .any(|rrset| rrset.subname.is_empty() && rrset.r#type == "CAA")
+    {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            subname: String::from("@"),
+            r#type: String::from("CAA"),
+            message: "No CAA record, every certificate authority may issue certificates"
+                .to_string(),
+        });
+    }
+    findings.sort_by(|a, b| {
+        (a.severity, &a.subname, &a.r#type).cmp(&(b.severity, &b.subname, &b.r#type))
+    });
+    findings
+}
+
+// The names the records of the rrset point to, fully qualified with trailing dot.
+fn targets(rrset: &RrsetState) -> Vec<String> {
+    // The target is the last field of the record for each of these types
+    let field = match rrset.r#type.as_str() {
+        "CNAME" | "NS" => 0,
+        "MX" => 1,
+        "SRV" => 3,
+        _ => return vec![],
+    };
+    rrset
+        .records
+        .iter()
+        .filter_map(|record| record.split_whitespace().nth(field))
+        .map(|target| target.to_ascii_lowercase())
+        // A null MX or SRV record says that there is no such service
+        .filter(|target| target != ".")
+        .collect()
+}
+
+// The subname of the target within the zone, None for targets outside of it.
+fn in_zone(target: &str, domain: &str) -> Option<String> {
+    let target = match target.strip_suffix('.') {
+        Some(target) => target.to_string(),
+        // Relative names of zone files are relative to the domain
+        None => format!("{target}.{domain}"),
+    };
+    match target.strip_suffix(domain)? {
+        "" => Some(String::new()),
+        subname => Some(subname.strip_suffix('.')?.to_string()),
+    }
+}
+
+// Whether a wildcard record of the zone covers the subname.
+fn wildcard(names: &BTreeSet<&str>, subname: &str) -> bool {
+    let mut parent = subname;
+    while !parent.is_empty() {
+        parent = parent.split_once('.').map_or("", |(_, parent)| parent);
+        let wildcard = match parent {
+            "" => String::from("*"),
+            parent => format!("*.{parent}"),
+        };
+        if names.contains(wildcard.as_str()) {
+            return true;
+        }
+    }
+    false
+}
+
+// Splits TXT record data into its character strings, resolving escapes.
+fn txt_strings(record: &str) -> Vec<String> {
+    if !record.trim_start().starts_with('"') {
+        return vec![record.trim().to_string()];
+    }
+    let mut strings = vec![];
+    let mut chars = record.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => {
+                    let Some(escaped) = chars.next() else {
+                        break;
+                    };
+                    // \DDD is a single byte given as decimal number
+                    if escaped.is_ascii_digit() {
+                        chars.next();
+                        chars.next();
+                        string.push('?');
+                    } else {
+                        string.push(escaped);
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rrset(subname: &str, r#type: &str, records: &[&str]) -> RrsetState {
+        RrsetState {
+            subname: subname.to_string(),
+            r#type: r#type.to_string(),
+            ttl: 3600,
+            records: records.iter().map(|record| record.to_string()).collect(),
+        }
+    }
+
+    fn caa() -> RrsetState {
+        rrset("", "CAA", &["0 issue \"letsencrypt.org\""])
+    }
+
+    // The messages of the findings of the rrsets, with a CAA record so only the checked rule reports.
+    fn messages(rrsets: &[RrsetState]) -> Vec<(Severity, String, String)> {
+        let mut rrsets = rrsets.to_vec();
+        rrsets.push(caa());
+        lint("example.com.", &rrsets, 3600)
+            .into_iter()
+            .map(|finding| (finding.severity, finding.subname, finding.message))
+            .collect()
+    }
+
+    #[test]
+    fn clean_zone() {
+        let rrsets = [
+            rrset("", "MX", &["10 mail.example.com."]),
+            rrset("mail", "A", &["192.0.2.1"]),
+            rrset("www", "CNAME", &["example.net."]),
+        ];
+        assert!(messages(&rrsets).is_empty());
+    }
+
+    #[test]
+    fn missing_caa() {
+        let findings = lint("example.com", &[rrset("www", "A", &["192.0.2.1"])], 3600);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].r#type, "CAA");
+    }
+
+    #[test]
+    fn ttl_below_minimum() {
+        let mut low = rrset("www", "A", &["192.0.2.1"]);
+        low.ttl = 60;
+        assert_eq!(
+            messages(&[low]),
+            vec![(
+                Severity::Error,
+                String::from("www"),
+                String::from("TTL 60 is below the minimum TTL 3600 of the domain")
+            )]
+        );
+    }
+
+    #[test]
+    fn cname_at_apex_and_next_to_other_records() {
+        let findings = messages(&[
+            rrset("", "CNAME", &["example.net."]),
+            rrset("www", "CNAME", &["example.net."]),
+            rrset("www", "TXT", &["\"text\""]),
+        ]);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].1, "@");
+        assert!(findings[0].2.starts_with("CNAME at the zone apex"));
+        assert_eq!(findings[1].1, "www");
+        assert_eq!(findings[1].2, "CNAME next to other records: TXT");
+    }
+
+    #[test]
+    fn multiple_spf_records() {
+        let findings = messages(&[rrset(
+            "",
+            "TXT",
+            &["\"v=spf1 mx -all\"", "\"v=spf1\" \" a -all\"", "\"other\""],
+        )]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].2,
+            "2 SPF records, receivers treat this as an error"
+        );
+    }
+
+    #[test]
+    fn long_txt_strings() {
+        let long = format!("\"{}\"", "a".repeat(256));
+        let split = format!("\"{}\" \"{}\"", "a".repeat(255), "a");
+        let findings = messages(&[
+            rrset("long", "TXT", &[&long]),
+            rrset("split", "TXT", &[&split]),
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].1, "long");
+        assert!(findings[0].2.starts_with("String of 256 bytes"));
+    }
+
+    #[test]
+    fn dangling_targets() {
+        let findings = messages(&[
+            rrset("", "MX", &["10 mail.example.com.", "20 backup"]),
+            rrset("_sip._tcp", "SRV", &["0 5 5060 sip.example.com."]),
+            rrset("www", "CNAME", &["web.example.com."]),
+            rrset("null", "MX", &["0 ."]),
+            rrset("backup", "A", &["192.0.2.2"]),
+        ]);
+        let targets: Vec<&str> = findings
+            .iter()
+            .map(|(_, _, message)| message.as_str())
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                "Target mail.example.com. has no records in the zone",
+                "Target sip.example.com. has no records in the zone",
+                "Target web.example.com. has no records in the zone",
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_covered_by_wildcards_and_delegations() {
+        let findings = messages(&[
+            rrset("", "MX", &["10 mail.example.com."]),
+            rrset("*", "A", &["192.0.2.1"]),
+            rrset("www", "CNAME", &["host.sub.example.com."]),
+            rrset("sub", "NS", &["ns.example.net."]),
+        ]);
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn target_is_cname() {
+        let findings = messages(&[
+            rrset("", "MX", &["10 mail.example.com."]),
+            rrset("mail", "CNAME", &["example.net."]),
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].2,
+            "Target mail.example.com. is a CNAME, which is not allowed for MX"
+        );
+    }
+
+    #[test]
+    fn txt_strings_escapes() {
+        assert_eq!(txt_strings(r#""a\"b" "c\\d""#), vec!["a\"b", "c\\d"]);
+        assert_eq!(txt_strings(r#""\226\130\172""#), vec!["???"]);
+        assert_eq!(txt_strings("unquoted"), vec!["unquoted"]);
+    }
+}
@@ -0,0 +1,54 @@
+use env_logger::Builder;
+use log::{Level, LevelFilter};
+use std::io::Write;
+
+/// Sets up the logger all messages besides the output of the commands go through.
+///
+/// Errors and status messages are logged at info level, so `--quiet` leaves only the output.
+/// `-v` adds the requests to the API with their status, timing and retries, `-vv` their bodies
+/// and `-vvv` the logs of all libraries. `RUST_LOG` takes precedence over both flags.
+pub fn init(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Off,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    let libraries = match verbose {
+        0..=2 => level.min(LevelFilter::Warn),
+        _ => level,
+    };
+    Builder::new()
+        .filter_level(libraries)
+        .filter_module("desec_cli", level)
+        .filter_module("desec_api", level)
+        .format(|buf, record| match record.level() {
+            Level::Error | Level::Warn | Level::Info => writeln!(buf, "{}", record.args()),
+            level => writeln!(
+                buf,
+                "[{} {}] {}",
+                level.as_str().to_ascii_lowercase(),
+                record.target(),
+                record.args()
+            ),
+        })
+        .parse_default_env()
+        .init();
+}
+
+/// Stops all logging until it is dropped, e.g. while the terminal UI covers the screen
+/// and messages written to stderr would corrupt it.
+pub struct Paused(LevelFilter);
+
+impl Drop for Paused {
+    fn drop(&mut self) {
+        log::set_max_level(self.0);
+    }
+}
+
+/// Pauses logging, the previous level is restored when the returned guard is dropped.
+pub fn pause() -> Paused {
+    let level = log::max_level();
+    log::set_max_level(LevelFilter::Off);
+    Paused(level)
+}
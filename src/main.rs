@@ -1,22 +1,42 @@
 use clap::{CommandFactory, Parser};
-use desec_api::{account, Client, Error};
+use clap_complete::CompleteEnv;
+use confirm::confirm;
+use desec_cli::api::token::{PolicyFields, TokenFields};
+use desec_cli::api::{trace, Client, Patch};
+use desec_cli::command::rrset::MigrationEvent;
+use desec_cli::config::Config;
+use desec_cli::migrate::{self, Migration};
+use desec_cli::rrset_name::RrsetName;
+use desec_cli::{changeset, command, dns, edit, lint, rrset_name, template, zone};
+use log::{debug, error, info, warn};
 use std::env;
+use std::path::PathBuf;
 use std::process::ExitCode;
 
 mod cli;
+mod complete;
+mod confirm;
+mod dispatch;
+mod dry_run;
+mod logging;
+mod output;
+mod review;
+mod tui;
 
 use cli::*;
+use dispatch::{dispatch, Context, RunnableCommand};
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    #[cfg(feature = "logging")]
-    env_logger::init();
+    // Answers dynamic completion requests of the shell and exits
+    CompleteEnv::with_factory(Cli::command).complete();
 
     let cli = Cli::parse();
+    logging::init(cli.quiet, cli.verbose);
 
     if let Some(generator) = cli.generator {
         let mut cmd = Cli::command();
-        eprintln!("Generating completion file for {generator:?}...");
+        info!("Generating completion file for {generator:?}...");
         print_completions(generator, &mut cmd);
         return ExitCode::SUCCESS;
     }
@@ -28,202 +48,52 @@ async fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
-    match cli.command.as_ref().unwrap() {
-        Command::Account(subcommand) => match &subcommand.command {
-            AccountCommand::Captcha => return get_captcha().await,
-            AccountCommand::Register(args) => return register(args).await,
-            AccountCommand::Login(args) => return login(args).await,
-            AccountCommand::RequestPasswordReset(args) => {
-                return request_password_reset(args).await
-            }
-            AccountCommand::ConfirmPasswordReset(args) => {
-                return confirm_password_reset(args).await
-            }
-            AccountCommand::UpdateOutreachPreference(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return update_outreach_preference(&client, args).await;
-            }
-            AccountCommand::Show => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return show_account(&client).await;
-            }
-        },
-        Command::Domain(args) => match &args.command {
-            DomainCommand::List => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return list_domains(&client).await;
-            }
-            DomainCommand::Get(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_domain(&client, args).await;
-            }
-            DomainCommand::Create(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return create_domain(&client, args).await;
-            }
-            DomainCommand::Delete(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return delete_domain(&client, args).await;
-            }
-            DomainCommand::Responsible(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_domain_responsible(&client, args).await;
-            }
-            DomainCommand::Export(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return export_domain(&client, args).await;
-            }
-        },
-        Command::ResourceRecordSet(subcommand) => match &subcommand.command {
-            ResourceRecordSetCommand::List(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_all_rrsets(&client, args).await;
-            }
-            ResourceRecordSetCommand::Get(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_rrset(&client, args).await;
-            }
-            ResourceRecordSetCommand::Create(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return create_rrset(&client, args).await;
-            }
-            ResourceRecordSetCommand::Delete(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return delete_rrset(&cli, &client, args).await;
-            }
-        },
-        Command::Token(subcommand) => match &subcommand.command {
-            TokenCommand::List => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return list_token(&client).await;
-            }
-            TokenCommand::Get(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_token(&client, args).await;
-            }
-            TokenCommand::Create(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return create_token(&client, args).await;
-            }
-            TokenCommand::Delete(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return delete_token(&client, args).await;
-            }
-            TokenCommand::Patch(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return patch_token(&client, args).await;
-            }
-        },
-        Command::TokenPolicy(subcommand) => match &subcommand.command {
-            TokenPolicyCommand::List(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return list_token_policies(&client, args).await;
-            }
-            TokenPolicyCommand::Create(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return create_token_policy(&client, args).await;
-            }
-            TokenPolicyCommand::Get(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return get_token_policy(&client, args).await;
-            }
-            TokenPolicyCommand::Patch(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return patch_token_policy(&client, args).await;
-            }
-            TokenPolicyCommand::Delete(args) => {
-                let client = match create_client(&cli).await {
-                    Some(client) => client,
-                    None => return ExitCode::FAILURE,
-                };
-                return delete_token_policy(&client, args).await;
-            }
-        },
+    if let Some(path) = &cli.trace_http {
+        let path = (path.as_os_str() != "-").then_some(path.as_path());
+        if let Err(error) = trace::start(path) {
+            error!("Failed to create the trace file: {error}");
+            return ExitCode::FAILURE;
+        }
     }
+
+    let command = cli.command.as_ref().unwrap();
+    let exit_code = match Context::new(&cli, command.requires_auth()).await {
+        Some(ctx) => dispatch(command, &ctx).await,
+        None => ExitCode::FAILURE,
+    };
+    if let Err(error) = trace::finish() {
+        error!("Failed to write the trace file: {error}");
+    }
+    exit_code
 }
 
 async fn create_client(cli: &Cli) -> Option<Client> {
-    // Create a new client from either a token from env var DESEC_API_TOKEN
-    // or from credentials in env vars DESEC_EMAIL & DESEC_PASSWORD.
-    let mut client = if let Ok(token) = env::var("DESEC_API_TOKEN") {
-        match Client::new(token) {
-            Ok(c) => c,
-            Err(Error::ReqwestClientBuilder(e)) => panic!("{e}"),
-            _ => unreachable!(),
+    let token = resolve_token().await?;
+    client_from_token(cli, &token)
+}
+
+// Creates the client for the given profile of the config file, or from the env vars without one.
+async fn create_profile_client(cli: &Cli, profile: Option<&str>) -> Option<Client> {
+    let Some(profile) = profile else {
+        return create_client(cli).await;
+    };
+    let token = match Config::load().and_then(|config| config.profile_token(profile)) {
+        Ok(token) => token,
+        Err(error) => {
+            error!("{error}");
+            return None;
         }
-    } else if let (Ok(email), Ok(password)) = (env::var("DESEC_EMAIL"), env::var("DESEC_PASSWORD"))
-    {
-        match Client::new_from_credentials(&email, &password).await {
-            Ok(c) => c,
-            Err(Error::ReqwestClientBuilder(e)) => panic!("{e}"),
-            _ => unreachable!(),
+    };
+    client_from_token(cli, &token)
+}
+
+fn client_from_token(cli: &Cli, token: &str) -> Option<Client> {
+    let mut client = match Client::new(token) {
+        Ok(c) => c,
+        Err(error) => {
+            error!("Failed to create the client: {error}");
+            return None;
         }
-    } else {
-        eprintln!("Missing env var TOKEN_ENV_VAR");
-        return None;
     };
 
     if let Some(max_retries) = cli.max_retries {
@@ -239,612 +109,864 @@ async fn create_client(cli: &Cli) -> Option<Client> {
     Some(client)
 }
 
-async fn get_captcha() -> ExitCode {
-    let captcha = match account::get_captcha().await {
-        Ok(captcha) => captcha,
-        Err(error) => {
-            eprintln!("An error occurred: {}", error);
-            return ExitCode::FAILURE;
+async fn resolve_token() -> Option<String> {
+    // Use either a token from env var DESEC_API_TOKEN
+    // or login with credentials in env vars DESEC_EMAIL & DESEC_PASSWORD.
+    if let Ok(token) = env::var("DESEC_API_TOKEN") {
+        Some(token)
+    } else if let (Ok(email), Ok(password)) = (env::var("DESEC_EMAIL"), env::var("DESEC_PASSWORD"))
+    {
+        match command::account::login(&email, &password).await {
+            Ok(login) => Some(login.token),
+            Err(error) => {
+                error!("{error}");
+                None
+            }
         }
-    };
-    let captcha_json = match serde_json::to_string(&captcha) {
-        Ok(json) => json,
-        Err(error) => panic!("{}", error),
-    };
-    println!("{captcha_json}");
-    ExitCode::SUCCESS
+    } else {
+        error!("Missing env var TOKEN_ENV_VAR");
+        None
+    }
+}
+
+async fn get_captcha() -> ExitCode {
+    match command::account::captcha().await {
+        Ok(captcha) => output::print(&captcha),
+        Err(error) => fail(error),
+    }
 }
 
 async fn register(args: &RegisterArgs) -> ExitCode {
-    let account = match account::register(
+    let account = command::account::register(
         &args.email,
         &args.password,
         &args.id,
         &args.solution,
         args.domain.as_deref(),
-    )
-    .await
-    {
-        Ok(account) => account,
-        Err(error) => {
-            eprintln!("An error occurred: {}", error);
-            return ExitCode::FAILURE;
-        }
-    };
-    let account_json = match serde_json::to_string(&account) {
-        Ok(json) => json,
-        Err(error) => panic!("{}", error),
-    };
-    println!("{account_json}");
-    ExitCode::SUCCESS
+    );
+    match account.await {
+        Ok(account) => output::print(&account),
+        Err(error) => fail(error),
+    }
 }
 
 async fn login(args: &LoginArgs) -> ExitCode {
-    let login = match account::login(&args.email, &args.password).await {
-        Ok(login) => login,
-        Err(Error::ReqwestClientBuilder(e)) => panic!("{e}"),
-        _ => unreachable!(),
-    };
-    let account_json = match serde_json::to_string(&login) {
-        Ok(json) => json,
-        Err(error) => panic!("{}", error),
-    };
-    println!("{account_json}");
-    ExitCode::SUCCESS
+    match command::account::login(&args.email, &args.password).await {
+        Ok(login) => output::print(&login),
+        Err(error) => fail(error),
+    }
 }
 
 async fn request_password_reset(args: &RequestPasswordResetArgs) -> ExitCode {
-    match account::request_password_reset(&args.email, &args.captcha_id, &args.captcha_solution)
-        .await
-    {
-        Ok(_) => {
+    let reset = command::account::request_password_reset(
+        &args.email,
+        &args.captcha_id,
+        &args.captcha_solution,
+    );
+    match reset.await {
+        Ok(()) => {
             println!(
                 "You should receive an email in your mailbox {} if such an account exists",
                 &args.email
             );
+            ExitCode::SUCCESS
         }
-        Err(Error::ReqwestClientBuilder(e)) => {
-            eprintln!("Failed to build the request: {e}");
-            return ExitCode::FAILURE;
-        }
-        Err(Error::ApiError(e, m)) => {
-            eprintln!("The API responded with error: {e} {m}");
-            return ExitCode::FAILURE;
-        }
-        Err(e) => {
-            eprintln!("An unexpected error occurred: {e}");
-            return ExitCode::FAILURE;
-        }
+        Err(error) => fail(error),
     }
-    ExitCode::SUCCESS
 }
 
 async fn confirm_password_reset(args: &ConfirmPasswordResetArgs) -> ExitCode {
-    match account::confirm_password_reset(&args.password, &args.code).await {
+    match command::account::confirm_password_reset(&args.password, &args.code).await {
         Ok(_) => {
             println!("Your password has successfully been set to the given password");
+            ExitCode::SUCCESS
         }
-        Err(Error::ReqwestClientBuilder(e)) => {
-            eprintln!("Failed to build the request: {e}");
-            return ExitCode::FAILURE;
-        }
-        Err(Error::ApiError(e, m)) => {
-            eprintln!("The API responded with error: {e} {m}");
-            return ExitCode::FAILURE;
-        }
-        Err(e) => {
-            eprintln!("An unexpected error occurred: {e}");
-            return ExitCode::FAILURE;
-        }
+        Err(error) => fail(error),
     }
-    ExitCode::SUCCESS
 }
 
 async fn update_outreach_preference(
     client: &Client,
     args: &UpdateOutreachPreferenceArgs,
 ) -> ExitCode {
-    let account_info = match client
-        .account()
-        .update_outreach_preference(args.outreach_preference)
-        .await
-    {
-        Ok(info) => info,
-        Err(Error::ReqwestClientBuilder(e)) => {
-            eprintln!("Failed to build the request: {e}");
-            return ExitCode::FAILURE;
-        }
-        Err(Error::ApiError(e, m)) => {
-            eprintln!("The API responded with error: {e} {m}");
-            return ExitCode::FAILURE;
-        }
-        Err(e) => {
-            eprintln!("An unexpected error occurred: {e}");
-            return ExitCode::FAILURE;
-        }
-    };
-    let account_info_json = match serde_json::to_string(&account_info) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to parse the API response: {error}");
-            return ExitCode::FAILURE;
-        }
-    };
-    println!("{account_info_json}");
-    ExitCode::SUCCESS
+    match command::account::update_outreach_preference(client, args.outreach_preference).await {
+        Ok(info) => output::print(&info),
+        Err(error) => fail(error),
+    }
 }
 
 async fn show_account(client: &Client) -> ExitCode {
-    let account_info = match client.account().get_account_info().await {
-        Ok(info) => info,
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
-        }
-    };
-    let account_info_json = match serde_json::to_string(&account_info) {
-        Ok(json) => json,
-        Err(error) => panic!("{}", error),
-    };
-    println!("{account_info_json}");
-    ExitCode::SUCCESS
+    match command::account::info(client).await {
+        Ok(info) => output::print(&info),
+        Err(error) => fail(error),
+    }
 }
 
 async fn create_domain(client: &Client, args: &DomainNameArg) -> ExitCode {
-    let domain_name = &args.name;
-    let domain = match client.domain().create_domain(domain_name).await {
-        Ok(domain) => domain,
-        Err(error) => {
-            eprintln!("Creation of domain failed: {}", error);
-            return ExitCode::FAILURE;
-        }
-    };
-    let domain_json = match serde_json::to_string(&domain) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize the returned domain: {error}");
-            return ExitCode::FAILURE;
-        }
-    };
-    println!("{domain_json}");
-    ExitCode::SUCCESS
+    match command::domain::create(client, &args.name).await {
+        Ok(domain) => output::print(&domain),
+        Err(error) => fail(error),
+    }
 }
 
-async fn list_domains(client: &Client) -> ExitCode {
-    let domains = match client.domain().get_domains().await {
-        Ok(domains) => domains,
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
-        }
-    };
-    let domains_json = match serde_json::to_string(&domains) {
-        Ok(json) => json,
-        Err(error) => panic!("{}", error),
-    };
-    println!("{domains_json}");
-    ExitCode::SUCCESS
+async fn list_domains(cli: &Cli, client: &Client) -> ExitCode {
+    match command::domain::list(client).await {
+        Ok(domains) => output::print_list(cli, &domains),
+        Err(error) => fail(error),
+    }
 }
 
 async fn get_domain(client: &Client, args: &DomainNameArg) -> ExitCode {
-    let domain_name = &args.name;
-    let domain = match client.domain().get_domain(domain_name).await {
-        Ok(domain) => domain,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "Domain {} does not exist or you are not the owner",
-                domain_name
-            );
-            return ExitCode::FAILURE;
-        }
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
-        }
-    };
-    let domain_json = match serde_json::to_string(&domain) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize the data: {error}");
-            return ExitCode::FAILURE;
-        }
-    };
-    println!("{domain_json}");
-    ExitCode::SUCCESS
+    match command::domain::get(client, &args.name).await {
+        Ok(domain) => output::print(&domain),
+        Err(error) => fail(error),
+    }
 }
 
 async fn get_domain_responsible(client: &Client, args: &DomainNameArg) -> ExitCode {
-    let domain_name = &args.name;
-    let domain = match client.domain().get_owning_domain(domain_name).await {
-        Ok(domain) => domain,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "Domain {} does not exist or you are not the owner",
-                domain_name
-            );
-            return ExitCode::FAILURE;
-        }
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
-        }
-    };
-    let domain_json = match serde_json::to_string(&domain) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize the data: {error}");
-            return ExitCode::FAILURE;
-        }
-    };
-    println!("{domain_json}");
-    ExitCode::SUCCESS
+    match command::domain::responsible(client, &args.name).await {
+        Ok(domain) => output::print(&domain),
+        Err(error) => fail(error),
+    }
 }
 
-async fn export_domain(client: &Client, args: &DomainNameArg) -> ExitCode {
-    let domain_name = &args.name;
-    let zonefile = match client.domain().get_zonefile(domain_name).await {
-        Ok(domain) => domain,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "Domain {} does not exist or you are not the owner",
-                domain_name
-            );
-            return ExitCode::FAILURE;
-        }
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
+async fn export_domain(client: &Client, args: &DomainExportArgs) -> ExitCode {
+    if args.local {
+        return match command::rrsets(client, &args.name).await {
+            Ok(rrsets) => print_zone(client, &args.name, &rrsets, &args.zone).await,
+            Err(error) => fail(error),
+        };
+    }
+    if args.zone.absolute || args.zone.dnssec {
+        error!("--absolute and --dnssec require --local");
+        return ExitCode::FAILURE;
+    }
+    match command::domain::zonefile(client, &args.name).await {
+        Ok(zonefile) => {
+            println!("{zonefile}");
+            ExitCode::SUCCESS
         }
-    };
-    println!("{zonefile}");
-    ExitCode::SUCCESS
+        Err(error) => fail(error),
+    }
 }
 
-async fn delete_domain(client: &Client, args: &DomainNameArg) -> ExitCode {
-    if let Err(error) = client.domain().delete_domain(&args.name).await {
-        eprintln!("Deletion of domain failed: {}", error);
-        return ExitCode::FAILURE;
-    };
-    ExitCode::SUCCESS
+async fn fcrdns_report(client: &Client, args: &DomainFcrdnsReportArgs) -> ExitCode {
+    match command::domain::fcrdns_report(client, args.parallel).await {
+        Ok(report) => output::print(&report),
+        Err(error) => fail(error),
+    }
 }
 
-async fn create_rrset(client: &Client, args: &ResourceRecordSetCreateArgs) -> ExitCode {
-    let subname = if args.subname == "@" {
-        None
-    } else {
-        Some(args.subname.as_str())
-    };
-    let rrset = match client
-        .rrset()
-        .create_rrset(&args.name, subname, &args.r#type, args.ttl, &args.records)
-        .await
+async fn lint_domain(cli: &Cli, client: &Client, args: &DomainLintArgs) -> ExitCode {
+    match command::domain::lint(client, &args.zone).await {
+        Ok(findings) => print_findings(cli, &findings),
+        Err(error) => fail(error),
+    }
+}
+
+fn lint_zone_file(cli: &Cli, args: &DomainLintArgs) -> ExitCode {
+    let path = std::path::Path::new(&args.zone);
+    match command::domain::lint_file(path, args.domain.as_deref(), args.minimum_ttl) {
+        Ok(findings) => print_findings(cli, &findings),
+        Err(error) => fail(error),
+    }
+}
+
+// Prints the findings of a lint, failing if any of them is an error so CI jobs fail.
+fn print_findings(cli: &Cli, findings: &[lint::Finding]) -> ExitCode {
+    let code = output::print_list(cli, findings);
+    match findings
+        .iter()
+        .any(|finding| finding.severity == lint::Severity::Error)
     {
-        Ok(rrset) => rrset,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "RRSet {} does not exist or you are not the owner",
-                args.name
-            );
-            return ExitCode::FAILURE;
+        true => ExitCode::FAILURE,
+        false => code,
+    }
+}
+
+async fn edit_domain(cli: &Cli, client: &Client, args: &DomainEditArgs) -> ExitCode {
+    let format = match args.format {
+        EditFormat::Zone => edit::Format::Zone,
+        EditFormat::Yaml => edit::Format::Yaml,
+    };
+    match command::domain::plan_edit(client, &args.name, format).await {
+        Ok(command::domain::EditPlan::Changes(changes)) if changes.is_empty() => {
+            info!("No changes");
+            ExitCode::SUCCESS
         }
-        Err(error) => {
-            eprintln!("An error occurred: {}", error);
-            return ExitCode::FAILURE;
+        Ok(command::domain::EditPlan::Changes(changes)) => {
+            review::apply_changes(cli, client, &changes, None).await
         }
-    };
-    let rrset_json = match serde_json::to_string(&rrset) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize the data: {error}");
-            return ExitCode::FAILURE;
+        Ok(command::domain::EditPlan::Cancelled(reason)) => {
+            info!("Edit cancelled, {reason}");
+            ExitCode::SUCCESS
         }
+        Err(error) => fail(error),
+    }
+}
+
+// Renders the rrsets as zone file, fetching the DNSSEC keys of the domain if requested.
+async fn print_zone(
+    client: &Client,
+    domain: &str,
+    rrsets: &[desec_api::rrset::ResourceRecordSet],
+    args: &ZoneArgs,
+) -> ExitCode {
+    let options = zone::ZoneOptions {
+        absolute: args.absolute,
     };
-    println!("{rrset_json}");
+    match command::domain::zone(client, domain, rrsets, args.dnssec, options).await {
+        Ok(zone) => {
+            print!("{zone}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => fail(error),
+    }
+}
 
-    ExitCode::SUCCESS
+// Fails if --if-unchanged-since is given and the domain has been changed after it.
+async fn check_unchanged_since(cli: &Cli, client: &Client, domain: &str) -> command::Result<()> {
+    match &cli.if_unchanged_since {
+        Some(since) => command::domain::check_unchanged_since(client, domain, since).await,
+        None => Ok(()),
+    }
 }
-async fn get_rrset(client: &Client, args: &ResourceRecordSetGetArgs) -> ExitCode {
-    let subname = if args.subname == "@" {
-        None
-    } else {
-        Some(args.subname.clone())
+
+// Prints the error of a command and returns the failure exit code.
+fn fail(error: command::Error) -> ExitCode {
+    error!("{error}");
+    ExitCode::FAILURE
+}
+
+// Refuses to delete a domain protected in the config unless forced.
+fn check_protected(args: &DomainDeleteArgs) -> command::Result<()> {
+    let config = Config::load().map_err(command::Error::Io)?;
+    match config.is_protected(&args.name) && !args.force_protected {
+        true => Err(command::Error::Invalid(format!(
+            "Domain {} is protected, pass --force-protected to delete it anyway",
+            args.name
+        ))),
+        false => Ok(()),
+    }
+}
+
+async fn delete_domain(cli: &Cli, client: &Client, args: &DomainDeleteArgs) -> ExitCode {
+    if let Err(error) = check_protected(args) {
+        return fail(error);
+    }
+    if let Err(error) = check_unchanged_since(cli, client, &args.name).await {
+        return fail(error);
+    }
+    if !cli.yes
+        && !confirm(
+            &format!("delete the domain {} with all its rrsets", args.name),
+            &args.name,
+        )
+    {
+        return ExitCode::FAILURE;
+    }
+    match command::domain::delete(client, &args.name).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => fail(error),
+    }
+}
+
+async fn clone_domain(cli: &Cli, args: &DomainCloneArgs) -> ExitCode {
+    let Some(source_client) = create_profile_client(cli, args.from_profile.as_deref()).await else {
+        return ExitCode::FAILURE;
     };
-    let rrset = match client
-        .rrset()
-        .get_rrset(&args.name, subname.as_deref(), &args.r#type)
-        .await
+    let Some(target_client) = create_profile_client(cli, args.to_profile.as_deref()).await else {
+        return ExitCode::FAILURE;
+    };
+    let plan = match command::domain::plan_clone(
+        &source_client,
+        &target_client,
+        &args.source,
+        &args.target,
+        &args.exclude_type,
+    )
+    .await
     {
-        Ok(rrset) => rrset,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "RRSet {} does not exist or you are not the owner",
-                args.name
-            );
-            return ExitCode::FAILURE;
-        }
-        Err(_) => {
-            eprintln!("An error occurred");
-            return ExitCode::FAILURE;
-        }
+        Ok(plan) => plan,
+        Err(error) => return fail(error),
     };
-    let rrset_json = match serde_json::to_string(&rrset) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize the data: {error}");
+    if plan.exists && plan.changes.is_empty() {
+        info!("Domain {} already contains all rrsets", args.target);
+        return ExitCode::SUCCESS;
+    }
+    if !plan.exists && cli.dry_run {
+        let preview = dry_run::Preview::new("POST", "/domains/")
+            .body(serde_json::json!({ "name": args.target }))
+            .effect(format!("Would create the domain {}", args.target));
+        if preview.print() == ExitCode::FAILURE {
             return ExitCode::FAILURE;
         }
+        println!();
+    }
+    if let Some(exit_code) = review::review_changes(cli, &plan.changes) {
+        return exit_code;
+    }
+    let since = cli.if_unchanged_since.as_ref();
+    match command::domain::clone(&target_client, &args.target, &plan, since).await {
+        Ok(results) if !plan.exists => {
+            info!("Created domain {}", args.target);
+            review::report(results)
+        }
+        Ok(results) => review::report(results),
+        Err(error) => fail(error),
+    }
+}
+
+async fn apply_template(cli: &Cli, client: &Client, args: &DomainApplyTemplateArgs) -> ExitCode {
+    match command::domain::plan_template(client, &args.name, &args.template, &args.vars).await {
+        Ok(changes) => apply_merge(cli, client, &args.name, &changes).await,
+        Err(error) => fail(error),
+    }
+}
+
+async fn apply_merge(
+    cli: &Cli,
+    client: &Client,
+    domain: &str,
+    changes: &[changeset::RrsetChange],
+) -> ExitCode {
+    if changes.is_empty() {
+        info!("Domain {domain} already contains the records");
+        return ExitCode::SUCCESS;
+    }
+    review::apply_changes(cli, client, changes, None).await
+}
+
+// Creates the resolver for names outside of deSEC, logging which one is used.
+fn resolver(url: Option<&str>) -> command::Result<dns::Resolver> {
+    let resolver = command::email::resolver(url)?;
+    debug!("Resolving names outside of deSEC with {}", resolver.url());
+    Ok(resolver)
+}
+
+async fn email_report(client: &Client, args: &EmailReportArgs) -> ExitCode {
+    let resolver = match resolver(args.resolver.as_deref()) {
+        Ok(resolver) => resolver,
+        Err(error) => return fail(error),
     };
-    println!("{rrset_json}");
-    ExitCode::SUCCESS
+    match command::email::report(client, &resolver, &args.name, &args.subname).await {
+        Ok(report) => output::print(&report),
+        Err(error) => fail(error),
+    }
 }
 
-async fn get_all_rrsets(client: &Client, args: &ResourceRecordSetListArgs) -> ExitCode {
-    let rrset = match client.rrset().get_rrsets(&args.name).await {
-        Ok(rrset) => rrset,
-        Err(Error::NotFound) => {
-            eprintln!(
-                "RRSet {} does not exist or you are not the owner",
-                args.name
-            );
-            return ExitCode::FAILURE;
-        }
-        Err(_) => {
-            eprintln!("An error occurred");
+async fn email_spf(cli: &Cli, client: &Client, args: &EmailSpfArgs) -> ExitCode {
+    let spf = command::email::Spf {
+        mx: args.mx,
+        a: args.a,
+        ip4: args.ip4.clone(),
+        ip6: args.ip6.clone(),
+        include: args.include.clone(),
+        all: String::from(match args.all {
+            SpfAll::Fail => "-all",
+            SpfAll::Softfail => "~all",
+            SpfAll::Neutral => "?all",
+        }),
+    };
+    let resolver = match resolver(args.resolver.as_deref()) {
+        Ok(resolver) => resolver,
+        Err(error) => return fail(error),
+    };
+    let plan =
+        command::email::plan_spf(client, &resolver, &args.name, &args.subname, args.ttl, &spf);
+    let plan = match plan.await {
+        Ok(plan) => plan,
+        Err(error) => return fail(error),
+    };
+    if !plan.check.problems.is_empty() {
+        for problem in &plan.check.problems {
+            error!("{problem}");
+        }
+        if !args.force {
+            error!("Pass --force to set the policy anyway");
             return ExitCode::FAILURE;
         }
+    }
+    apply_merge(cli, client, &args.name, &plan.changes).await
+}
+
+async fn email_dkim(cli: &Cli, client: &Client, args: &EmailDkimArgs) -> ExitCode {
+    let plan = command::email::plan_dkim(
+        client,
+        &args.name,
+        &args.subname,
+        &args.selector,
+        &args.key_file,
+        args.ttl,
+    );
+    match plan.await {
+        Ok(changes) => apply_merge(cli, client, &args.name, &changes).await,
+        Err(error) => fail(error),
+    }
+}
+
+async fn email_dmarc(cli: &Cli, client: &Client, args: &EmailDmarcArgs) -> ExitCode {
+    let policy = |policy: DmarcPolicy| match policy {
+        DmarcPolicy::None => String::from("none"),
+        DmarcPolicy::Quarantine => String::from("quarantine"),
+        DmarcPolicy::Reject => String::from("reject"),
+    };
+    let alignment = |alignment: DmarcAlignment| match alignment {
+        DmarcAlignment::Relaxed => String::from("r"),
+        DmarcAlignment::Strict => String::from("s"),
+    };
+    let dmarc = command::email::Dmarc {
+        policy: policy(args.policy),
+        subdomain_policy: args.subdomain_policy.map(policy),
+        pct: args.pct,
+        rua: args.rua.clone(),
+        ruf: args.ruf.clone(),
+        adkim: args.adkim.map(alignment),
+        aspf: args.aspf.map(alignment),
+    };
+    let plan = command::email::plan_dmarc(client, &args.name, &args.subname, args.ttl, &dmarc);
+    match plan.await {
+        Ok(changes) => apply_merge(cli, client, &args.name, &changes).await,
+        Err(error) => fail(error),
+    }
+}
+
+async fn email_mta_sts(cli: &Cli, client: &Client, args: &EmailMtaStsArgs) -> ExitCode {
+    let mta_sts = command::email::MtaSts {
+        id: args.id.clone(),
+        rua: args.rua.clone(),
+        policy_host: args.policy_host.clone(),
     };
-    let rrset_json = match serde_json::to_string(&rrset) {
-        Ok(json) => json,
+    let plan = command::email::plan_mta_sts(client, &args.name, &args.subname, args.ttl, &mta_sts);
+    match plan.await {
+        Ok(changes) => apply_merge(cli, client, &args.name, &changes).await,
+        Err(error) => fail(error),
+    }
+}
+
+fn list_templates() -> ExitCode {
+    output::print(&template::list())
+}
+
+async fn create_rrset(cli: &Cli, client: &Client, args: &ResourceRecordSetCreateArgs) -> ExitCode {
+    let (name, ttl, records) = match rrset_name::resolve_with_records(client, &args.rrset).await {
+        Ok(resolved) => resolved,
         Err(error) => {
-            eprintln!("Failed to serialize the data: {error}");
+            error!("{error}");
             return ExitCode::FAILURE;
         }
     };
-    println!("{rrset_json}");
-    ExitCode::SUCCESS
+    if let Err(error) = check_unchanged_since(cli, client, &name.domain).await {
+        return fail(error);
+    }
+    match command::rrset::create(client, &name, ttl, &records).await {
+        Ok(rrset) => output::print(&rrset),
+        Err(error) => fail(error),
+    }
 }
 
-async fn delete_rrset(cli: &Cli, client: &Client, args: &ResourceRecordSetDeleteArgs) -> ExitCode {
-    let subname = if args.subname == "@" {
-        None
-    } else {
-        Some(args.subname.as_str())
-    };
-    match client
-        .rrset()
-        .delete_rrset(&args.name, subname, &args.r#type)
-        .await
-    {
-        Ok(_) => {
-            if !cli.quiet {
-                eprintln!(
-                    "rrset {} {}.{} has been deleted or did not exist",
-                    args.r#type, args.subname, args.name
-                )
-            }
-        }
-        Err(Error::NotFound) => {
-            if !cli.quiet {
-                eprintln!(
-                    "RRSet {} does not exist or you are not the owner",
-                    args.name
-                );
-            }
-            return ExitCode::FAILURE;
-        }
+async fn get_rrset(client: &Client, args: &ResourceRecordSetGetArgs) -> ExitCode {
+    let name = match rrset_name::resolve(client, &args.rrset).await {
+        Ok(name) => name,
         Err(error) => {
-            if !cli.quiet {
-                eprintln!(
-                    "Deletion of rrset {} {}.{} failed: {}",
-                    args.r#type, args.subname, args.name, error
-                );
-            }
+            error!("{error}");
             return ExitCode::FAILURE;
         }
     };
-    ExitCode::SUCCESS
+    match command::rrset::get(client, &name).await {
+        Ok(rrset) => output::print(&rrset),
+        Err(error) => fail(error),
+    }
 }
 
-async fn list_token(client: &Client) -> ExitCode {
-    let tokens = match client.token().list().await {
-        Ok(rrset) => rrset,
+async fn get_all_rrsets(cli: &Cli, client: &Client, args: &ResourceRecordSetListArgs) -> ExitCode {
+    let value = match args.pattern.as_deref().map(regex::Regex::new).transpose() {
+        Ok(value) => value,
         Err(error) => {
-            eprintln!("Failed to list tokens: {}", error);
+            error!("Invalid match regex: {error}");
             return ExitCode::FAILURE;
         }
     };
-    let tokens_json = match serde_json::to_string(&tokens) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize tokin list: {error}");
+    let query = command::rrset::ListQuery {
+        subname: args.subname.clone(),
+        r#type: args.r#type.clone(),
+        value,
+        sort: args.sort.map(|sort| match sort {
+            RrsetSortKey::Subname => command::rrset::SortKey::Subname,
+            RrsetSortKey::Type => command::rrset::SortKey::Type,
+            RrsetSortKey::Ttl => command::rrset::SortKey::Ttl,
+        }),
+    };
+    let rrsets = match command::rrset::list(client, &args.name, &query).await {
+        Ok(rrsets) => rrsets,
+        Err(error) => return fail(error),
+    };
+    if cli.output == OutputFormat::Zone {
+        if !cli.fields.is_empty() {
+            error!("--fields cannot be used with --output zone");
             return ExitCode::FAILURE;
         }
-    };
-    println!("{tokens_json}");
-    ExitCode::SUCCESS
+        return print_zone(client, &args.name, &rrsets, &args.zone).await;
+    }
+    output::print_list(cli, &rrsets)
 }
 
-async fn get_token(client: &Client, args: &TokenIdArgs) -> ExitCode {
-    let tokens = match client.token().get(&args.token_id).await {
-        Ok(rrset) => rrset,
+async fn delete_rrset(cli: &Cli, client: &Client, args: &ResourceRecordSetDeleteArgs) -> ExitCode {
+    let name = match rrset_name::resolve(client, &args.rrset).await {
+        Ok(name) => name,
         Err(error) => {
-            eprintln!("Failed to get token: {}", error);
+            error!("{error}");
             return ExitCode::FAILURE;
         }
     };
-    let tokens_json = match serde_json::to_string(&tokens) {
-        Ok(json) => json,
+    if let Err(error) = check_unchanged_since(cli, client, &name.domain).await {
+        return fail(error);
+    }
+    // Deleting delegations or records at the apex can take the whole zone offline
+    let critical = name.subname().is_none()
+        || name.r#type.eq_ignore_ascii_case("NS")
+        || name.r#type.eq_ignore_ascii_case("DS");
+    if critical && !cli.yes && !confirm(&format!("delete the rrset {name}"), &name.domain) {
+        return ExitCode::FAILURE;
+    }
+    match command::rrset::delete(client, &name).await {
+        Ok(()) => {
+            info!("rrset {name} has been deleted or did not exist");
+            ExitCode::SUCCESS
+        }
         Err(error) => {
-            eprintln!("Failed to serialize tokin list: {error}");
-            return ExitCode::FAILURE;
+            error!("{error}");
+            ExitCode::FAILURE
         }
-    };
-    println!("{tokens_json}");
-    ExitCode::SUCCESS
+    }
 }
 
-async fn create_token(client: &Client, args: &TokenCreateArgs) -> ExitCode {
-    let tokens = match client
-        .token()
-        .create(
-            args.name.clone(),
-            args.subnets.clone(),
-            args.manage,
-            args.max_age.clone(),
-            args.max_unused_period.clone(),
-        )
-        .await
-    {
-        Ok(rrset) => rrset,
-        Err(error) => {
-            eprintln!("Failed to get token: {}", error);
-            return ExitCode::FAILURE;
-        }
+async fn search_rrsets(cli: &Cli, client: &Client, args: &ResourceRecordSetSearchArgs) -> ExitCode {
+    let filter = match command::rrset::search_filter(
+        args.value.as_deref(),
+        &args.types,
+        args.subname.as_deref(),
+        args.min_ttl,
+        args.max_ttl,
+    ) {
+        Ok(filter) => filter,
+        Err(error) => return fail(error),
+    };
+    let domains = match command::domain::names(client, args.domain.as_deref()).await {
+        Ok(domains) => domains,
+        Err(error) => return fail(error),
     };
-    let tokens_json = match serde_json::to_string(&tokens) {
-        Ok(json) => json,
-        Err(error) => {
-            eprintln!("Failed to serialize tokin list: {error}");
-            return ExitCode::FAILURE;
+    let mut exit_code = ExitCode::SUCCESS;
+    let mut matches = vec![];
+    for (_, result) in command::rrset::search(client, &domains, &filter, args.parallel).await {
+        match result {
+            Ok(rrsets) => matches.extend(rrsets),
+            Err(error) => exit_code = fail(error),
         }
-    };
-    println!("{tokens_json}");
-    ExitCode::SUCCESS
+    }
+    if output::print_list(cli, &matches) == ExitCode::FAILURE {
+        return ExitCode::FAILURE;
+    }
+    exit_code
 }
 
-async fn patch_token(client: &Client, args: &TokenPatchArgs) -> ExitCode {
-    let tokens = match client
-        .token()
-        .patch(
-            &args.token_id,
-            args.name.clone(),
-            args.subnets.clone(),
-            args.manage,
-            args.max_age.clone(),
-            args.max_unused_period.clone(),
-        )
-        .await
+async fn replace_rrsets(
+    cli: &Cli,
+    client: &Client,
+    args: &ResourceRecordSetReplaceArgs,
+) -> ExitCode {
+    let domains = match command::domain::names(client, args.domain.as_deref()).await {
+        Ok(domains) => domains,
+        Err(error) => return fail(error),
+    };
+    let changes = match command::rrset::plan_replace(
+        client,
+        &domains,
+        &args.from,
+        &args.to,
+        &args.types,
+        args.parallel,
+    )
+    .await
     {
-        Ok(rrset) => rrset,
-        Err(error) => {
-            eprintln!("Failed to patch token: {}", error);
-            return ExitCode::FAILURE;
-        }
+        Ok(changes) => changes,
+        Err(error) => return fail(error),
+    };
+    if changes.is_empty() {
+        info!("No records contain {}", args.from);
+        return ExitCode::SUCCESS;
+    }
+    let undo_file = args
+        .undo_file
+        .clone()
+        .unwrap_or_else(command::rrset::default_undo_file);
+    review::apply_changes(cli, client, &changes, Some(undo_file)).await
+}
+
+async fn restore_rrsets(
+    cli: &Cli,
+    client: &Client,
+    args: &ResourceRecordSetRestoreArgs,
+) -> ExitCode {
+    let undo = match command::rrset::read_undo_file(&args.file) {
+        Ok(undo) => undo,
+        Err(error) => return fail(error),
     };
-    let tokens_json = match serde_json::to_string(&tokens) {
-        Ok(json) => json,
+    let changes = match command::rrset::plan_restore(client, undo).await {
+        Ok(changes) => changes,
         Err(error) => {
-            eprintln!("Failed to serialize tokin list: {error}");
+            error!("{error}, nothing has been changed");
             return ExitCode::FAILURE;
         }
     };
-    println!("{tokens_json}");
+    if changes.is_empty() {
+        info!("All rrsets already match the undo file");
+        return ExitCode::SUCCESS;
+    }
+    review::apply_changes(cli, client, &changes, None).await
+}
+
+// Loads the migration of the rrset, resuming the one saved in the state file.
+async fn load_migration(
+    client: &Client,
+    args: &ResourceRecordSetMigrateArgs,
+) -> command::Result<(RrsetName, PathBuf, Migration)> {
+    let name = rrset_name::resolve(client, &args.rrset)
+        .await
+        .map_err(command::Error::Invalid)?;
+    let path = args
+        .state_file
+        .clone()
+        .unwrap_or_else(|| Migration::default_path(&name));
+    let (migration, resumed) =
+        command::rrset::load_migration(client, &name, &args.to, &path).await?;
+    if resumed {
+        info!("Resuming the migration saved in {}", path.display());
+    }
+    Ok((name, path, migration))
+}
+
+async fn migrate_rrset(
+    cli: &Cli,
+    client: &Client,
+    args: &ResourceRecordSetMigrateArgs,
+) -> ExitCode {
+    let (name, path, mut migration) = match load_migration(client, args).await {
+        Ok(loaded) => loaded,
+        Err(error) => return fail(error),
+    };
+    let progress = |migration: &Migration, event| match event {
+        MigrationEvent::Waiting(wait) if migration.step == migrate::Step::Switched => info!(
+            "Waiting {wait} seconds before restoring the TTL of {name} to {}, \
+             so the switch can be reverted quickly until then",
+            migration.original.ttl
+        ),
+        MigrationEvent::Waiting(wait) => info!(
+            "Waiting {wait} seconds until resolvers no longer cache {name} with TTL {}, \
+             the migration can be interrupted and resumed by running the command again",
+            migration.original.ttl
+        ),
+        MigrationEvent::Written(migrate::Step::Lowered) => {
+            info!("Lowered the TTL of {name} to {}", migration.lowered_ttl)
+        }
+        MigrationEvent::Written(migrate::Step::Switched) => {
+            info!("Switched the records of {name}")
+        }
+        MigrationEvent::Written(_) => {
+            info!("Restored the TTL of {name} to {}", migration.original.ttl)
+        }
+    };
+    let since = cli.if_unchanged_since.as_ref();
+    match command::rrset::migrate(client, &mut migration, &path, since, progress).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => fail(error),
+    }
+}
+
+async fn tui(cli: &Cli, client: &Client) -> ExitCode {
+    match tui::run(cli, client).await {
+        Ok(changes) if changes.is_empty() => ExitCode::SUCCESS,
+        // Changes are only returned in dry-run mode, their requests are printed instead
+        Ok(changes) => review::review_changes(cli, &changes).unwrap_or(ExitCode::SUCCESS),
+        Err(error) => fail(error),
+    }
+}
+
+fn repo_init(args: &RepoInitArgs) -> ExitCode {
+    if let Err(error) = command::repo::init(&args.dir, &args.domain) {
+        return fail(error);
+    }
+    info!(
+        "Initialized zone repository, fetch the zones with: desec_cli repo pull {}",
+        args.dir.display()
+    );
     ExitCode::SUCCESS
 }
 
-async fn delete_token(client: &Client, args: &TokenIdArgs) -> ExitCode {
-    match client.token().delete(&args.token_id).await {
-        Ok(_) => (),
-        Err(error) => {
-            eprintln!("Failed to delete token: {}", error);
-            return ExitCode::FAILURE;
-        }
+async fn repo_pull(client: &Client, args: &RepoDirArgs) -> ExitCode {
+    let pull = match command::repo::pull(client, &args.dir).await {
+        Ok(pull) => pull,
+        Err(error) => return fail(error),
     };
-    ExitCode::SUCCESS
+    for error in &pull.failed {
+        error!("{error}");
+    }
+    // Files of deleted domains are left for the user to remove, they might hold the only copy
+    for domain in &pull.gone {
+        warn!("Domain {domain} no longer exists, its zone file is not updated anymore");
+    }
+    info!("Pulled {} domains", pull.pulled.len() + pull.failed.len());
+    match pull.failed.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
 }
 
-async fn get_token_policy(client: &Client, args: &TokenPolicyGetArgs) -> ExitCode {
-    match client
-        .token()
-        .get_policy(&args.token_id, &args.policy_id)
-        .await
-    {
-        Ok(response) => match serde_json::to_string(&response) {
-            Ok(json) => println!("{json}"),
-            Err(error) => {
-                eprintln!("Failed to serialize tokin policy: {error}");
-                return ExitCode::FAILURE;
-            }
-        },
-        Err(error) => {
-            eprintln!("Failed to get token policy: {}", error);
-            return ExitCode::FAILURE;
-        }
+async fn repo_push(cli: &Cli, client: &Client, args: &RepoDirArgs) -> ExitCode {
+    let plan = match command::repo::plan_push(client, &args.dir).await {
+        Ok(plan) => plan,
+        Err(error) => return fail(error),
     };
-    ExitCode::SUCCESS
+    if plan.changes.is_empty() {
+        info!("The zone files match the domains, nothing to push");
+        return ExitCode::SUCCESS;
+    }
+    let exit_code = review::apply_changes(cli, client, &plan.changes, None).await;
+    if cli.dry_run || exit_code == ExitCode::FAILURE {
+        return exit_code;
+    }
+    match command::repo::finish_push(client, &args.dir, &plan.domains).await {
+        Ok(()) => exit_code,
+        Err(error) => fail(error),
+    }
 }
 
-async fn list_token_policies(client: &Client, args: &TokenPolicyListArgs) -> ExitCode {
-    match client.token().list_policies(&args.token_id).await {
-        Ok(response) => match serde_json::to_string(&response) {
-            Ok(json) => println!("{json}"),
-            Err(error) => {
-                eprintln!("Failed to serialize tokin policy list: {error}");
-                return ExitCode::FAILURE;
-            }
-        },
-        Err(error) => {
-            eprintln!("Failed to get list of token policies: {}", error);
-            return ExitCode::FAILURE;
-        }
+async fn ptr_set(cli: &Cli, client: &Client, args: &PtrSetArgs) -> ExitCode {
+    let changes = match command::ptr::plan_set(client, args.ip, &args.hostname, args.ttl).await {
+        Ok(changes) => changes,
+        Err(error) => return fail(error),
     };
-    ExitCode::SUCCESS
+    if changes.is_empty() {
+        info!("{} already points to {}", args.ip, args.hostname);
+        return ExitCode::SUCCESS;
+    }
+    review::apply_changes(cli, client, &changes, None).await
 }
 
-async fn create_token_policy(client: &Client, args: &TokenPolicyCreateArgs) -> ExitCode {
-    match client
-        .token()
-        .create_policy(
-            &args.token_id,
-            args.domain.clone().filter(|d| !d.is_empty()),
-            args.subname.clone().filter(|s| !s.is_empty()),
-            args.r#type.clone().filter(|r| !r.is_empty()),
-            args.perm_write,
-        )
-        .await
-    {
-        Ok(_) => (),
-        Err(error) => {
-            eprintln!("Failed to create the token policies: {}", error);
-            return ExitCode::FAILURE;
-        }
+async fn ptr_sync(cli: &Cli, client: &Client, args: &PtrSyncArgs) -> ExitCode {
+    let plan = match command::ptr::plan_sync(client, &args.from_zone).await {
+        Ok(plan) => plan,
+        Err(error) => return fail(error),
     };
-    ExitCode::SUCCESS
+    for ip in &plan.unhosted {
+        warn!("No reverse zone of the account contains {ip}, skipping it");
+    }
+    if plan.changes.is_empty() {
+        info!("The PTR records of {} are up to date", args.from_zone);
+        return ExitCode::SUCCESS;
+    }
+    review::apply_changes(cli, client, &plan.changes, None).await
+}
+
+async fn list_token(cli: &Cli, client: &Client) -> ExitCode {
+    match command::token::list(client).await {
+        Ok(tokens) => output::print_list(cli, &tokens),
+        Err(error) => fail(error),
+    }
+}
+
+async fn get_token(client: &Client, args: &TokenIdArgs) -> ExitCode {
+    match command::token::get(client, &args.token_id).await {
+        Ok(token) => output::print(&token),
+        Err(error) => fail(error),
+    }
+}
+
+async fn create_token(client: &Client, args: &TokenCreateArgs) -> ExitCode {
+    let fields = TokenFields {
+        name: args.name.clone(),
+        allowed_subnets: args.subnets.clone(),
+        perm_manage_tokens: args.manage,
+        max_age: Patch::new(args.max_age.clone(), false),
+        max_unused_period: Patch::new(args.max_unused_period.clone(), false),
+    };
+    match command::token::create(client, fields).await {
+        Ok(token) => output::print(&token),
+        Err(error) => fail(error),
+    }
+}
+
+async fn patch_token(client: &Client, args: &TokenPatchArgs) -> ExitCode {
+    match command::token::patch(client, &args.token_id, args.patch()).await {
+        Ok(token) => output::print(&token),
+        Err(error) => fail(error),
+    }
+}
+
+async fn delete_token(client: &Client, args: &TokenIdArgs) -> ExitCode {
+    match command::token::delete(client, &args.token_id).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => fail(error),
+    }
+}
+
+async fn get_token_policy(client: &Client, args: &TokenPolicyGetArgs) -> ExitCode {
+    match command::token::get_policy(client, &args.token_id, &args.policy_id).await {
+        Ok(policy) => output::print(&policy),
+        Err(error) => fail(error),
+    }
+}
+
+async fn list_token_policies(cli: &Cli, client: &Client, args: &TokenPolicyListArgs) -> ExitCode {
+    match command::token::list_policies(client, &args.token_id).await {
+        Ok(policies) => output::print_list(cli, &policies),
+        Err(error) => fail(error),
+    }
+}
+
+async fn create_token_policy(client: &Client, args: &TokenPolicyCreateArgs) -> ExitCode {
+    let fields = PolicyFields {
+        domain: args.domain.clone().filter(|d| !d.is_empty()),
+        subname: args.subname.clone().filter(|s| !s.is_empty()),
+        r#type: args.r#type.clone().filter(|r| !r.is_empty()),
+        perm_write: args.perm_write.unwrap_or_default(),
+    };
+    match command::token::create_policy(client, &args.token_id, fields).await {
+        Ok(policy) => output::print(&policy),
+        Err(error) => fail(error),
+    }
+}
+
+// Reads the policy and computes the fields it would have after the patch.
+async fn plan_policy_patch(
+    client: &Client,
+    args: &TokenPolicyPatchArgs,
+) -> command::Result<command::token::PolicyPlan> {
+    let patch = command::token::PolicyPatch {
+        domain: Patch::new(args.domain.clone(), args.clear_domain),
+        subname: Patch::new(args.subname.clone(), args.clear_subname),
+        r#type: Patch::new(args.r#type.clone(), args.clear_type),
+        perm_write: args.perm_write,
+    };
+    command::token::plan_policy_patch(client, &args.token_id, &args.policy_id, patch).await
 }
 
 async fn patch_token_policy(client: &Client, args: &TokenPolicyPatchArgs) -> ExitCode {
-    match client
-        .token()
-        .patch_policy(
-            &args.token_id,
-            &args.policy_id,
-            args.domain.clone().filter(|d| !d.is_empty()),
-            args.subname.clone().filter(|s| !s.is_empty()),
-            args.r#type.clone().filter(|r| !r.is_empty()),
-            args.perm_write,
-        )
-        .await
-    {
-        Ok(_) => (),
-        Err(error) => {
-            eprintln!("Failed to create the token policies: {}", error);
-            return ExitCode::FAILURE;
-        }
-    };
-    ExitCode::SUCCESS
+    let plan = match plan_policy_patch(client, args).await {
+        Ok(plan) => plan,
+        Err(error) => return fail(error),
+    };
+    let patched =
+        command::token::patch_policy(client, &args.token_id, &args.policy_id, plan.fields);
+    match patched.await {
+        Ok(policy) => output::print(&policy),
+        Err(error) => fail(error),
+    }
 }
 
 async fn delete_token_policy(client: &Client, args: &TokenPolicyDeleteArgs) -> ExitCode {
-    match client
-        .token()
-        .delete_policy(&args.token_id, &args.policy_id)
-        .await
-    {
-        Ok(_) => (),
-        Err(error) => {
-            eprintln!("Failed to delete the token policies: {}", error);
-            return ExitCode::FAILURE;
-        }
-    };
-    ExitCode::SUCCESS
+    match command::token::delete_policy(client, &args.token_id, &args.policy_id).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => fail(error),
+    }
 }
@@ -0,0 +1,216 @@
+//! Changing the records of an rrset with a lowered TTL, so resolvers pick up the new records quickly.
+//!
+//! A migration lowers the TTL to the minimum of the domain, waits until resolvers no longer cache
+//! the rrset with its original TTL and switches the records. The original TTL is only restored
+//! after it has passed once more, so a broken switch can be reverted while the TTL is still low.
+//! Its progress is kept in a state file, so an interrupted migration continues where it stopped.
+
+use crate::changeset::{RrsetChange, RrsetState};
+use crate::rrset_name::RrsetName;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The last step of the migration which has been written.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Nothing has been written yet
+    Planned,
+    Lowered,
+    Switched,
+    Restored,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Migration {
+    pub domain: String,
+    /// The rrset before the migration, its TTL is restored at the end
+    pub original: RrsetState,
+    /// The records to switch to
+    pub records: Vec<String>,
+    /// The TTL during the migration, the minimum TTL of the domain
+    pub lowered_ttl: u64,
+    pub step: Step,
+    /// Unix time after which no resolver caches the rrset with its original TTL anymore
+    pub switch_after: u64,
+    /// Unix time after which the original TTL is restored
+    #[serde(default)]
+    pub restore_after: u64,
+}
+
+impl Migration {
+    pub fn new(domain: &str, original: RrsetState, records: &[String], minimum_ttl: u64) -> Self {
+        Migration {
+            domain: domain.to_string(),
+            lowered_ttl: minimum_ttl.min(original.ttl),
+            original,
+            records: records.to_vec(),
+            step: Step::Planned,
+            switch_after: 0,
+            restore_after: 0,
+        }
+    }
+
+    /// The state file used if none is given, in the current directory.
+    pub fn default_path(name: &RrsetName) -> PathBuf {
+        PathBuf::from(format!(
+            "desec-migrate-{}-{}-{}.json",
+            name.domain,
+            name.url_subname(),
+            name.r#type
+        ))
+    }
+
+    /// Loads the state of a started migration, None if there is no state file.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|error| format!("Invalid state file {}: {error}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| error.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|error| error.to_string()))
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+    }
+
+    /// Whether the migration changes the given rrset to the given records.
+    pub fn is_for(&self, name: &RrsetName, records: &[String]) -> bool {
+        self.domain == name.domain
+            && self.original.subname == name.subname
+            && self.original.r#type.eq_ignore_ascii_case(&name.r#type)
+            && self.records == records
+    }
+
+    /// The change of the next step, None once the migration is done.
+    pub fn next_change(&self) -> Option<RrsetChange> {
+        let lowered = RrsetState {
+            ttl: self.lowered_ttl,
+            ..self.original.clone()
+        };
+        let switched = RrsetState {
+            records: self.records.clone(),
+            ..lowered.clone()
+        };
+        let (before, after) = match self.step {
+            Step::Planned => (self.original.clone(), lowered),
+            Step::Lowered => (lowered, switched),
+            Step::Switched => (
+                switched.clone(),
+                RrsetState {
+                    ttl: self.original.ttl,
+                    ..switched
+                },
+            ),
+            Step::Restored => return None,
+        };
+        Some(RrsetChange {
+            domain: self.domain.clone(),
+            before: Some(before),
+            after,
+        })
+    }
+
+    /// Marks the next step as written.
+    pub fn advance(&mut self) {
+        self.step = match self.step {
+            Step::Planned => {
+                // Without a lower TTL waiting would not speed up the switch
+                if self.lowered_ttl < self.original.ttl {
+                    self.switch_after = now() + self.original.ttl;
+                }
+                Step::Lowered
+            }
+            Step::Lowered => {
+                if self.lowered_ttl < self.original.ttl {
+                    self.restore_after = now() + self.original.ttl;
+                }
+                Step::Switched
+            }
+            Step::Switched | Step::Restored => Step::Restored,
+        };
+    }
+
+    /// The seconds to wait before the next step, switching the records or restoring the TTL.
+    pub fn wait(&self) -> u64 {
+        match self.step {
+            Step::Lowered => self.switch_after.saturating_sub(now()),
+            Step::Switched => self.restore_after.saturating_sub(now()),
+            _ => 0,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migration(original_ttl: u64, minimum_ttl: u64) -> Migration {
+        let original = RrsetState {
+            subname: String::from("www"),
+            r#type: String::from("A"),
+            ttl: original_ttl,
+            records: vec![String::from("192.0.2.1")],
+        };
+        Migration::new(
+            "example.com",
+            original,
+            &[String::from("192.0.2.2")],
+            minimum_ttl,
+        )
+    }
+
+    #[test]
+    fn waits_before_switch_and_restore() {
+        let mut migration = migration(3600, 60);
+        assert_eq!(migration.wait(), 0);
+        migration.advance();
+        assert_eq!(migration.step, Step::Lowered);
+        assert!(migration.wait() > 3500);
+        migration.switch_after = 0;
+        migration.advance();
+        assert_eq!(migration.step, Step::Switched);
+        assert!(migration.wait() > 3500);
+        let change = migration.next_change().unwrap();
+        assert_eq!(change.before.unwrap().ttl, 60);
+        assert_eq!(change.after.ttl, 3600);
+        migration.advance();
+        assert_eq!(migration.step, Step::Restored);
+        assert_eq!(migration.wait(), 0);
+        assert!(migration.next_change().is_none());
+    }
+
+    #[test]
+    fn no_wait_without_lower_ttl() {
+        let mut migration = migration(60, 3600);
+        assert_eq!(migration.lowered_ttl, 60);
+        migration.advance();
+        assert_eq!(migration.wait(), 0);
+        migration.advance();
+        assert_eq!(migration.wait(), 0);
+    }
+
+    #[test]
+    fn load_state_without_restore_time() {
+        let mut state = serde_json::to_value(migration(3600, 60)).unwrap();
+        state.as_object_mut().unwrap().remove("restore_after");
+        let loaded: Migration = serde_json::from_value(state).unwrap();
+        assert_eq!(loaded.restore_after, 0);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::cli::{Cli, OutputFormat};
+use log::error;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// Prints the items of a list command in the selected output format,
+/// reduced to the fields given with `--fields`.
+pub fn print_list<T: Serialize>(cli: &Cli, items: &[T]) -> ExitCode {
+    let values = match items
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(values) => values,
+        Err(error) => {
+            error!("Failed to serialize the data: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let values = match project(values, &cli.fields) {
+        Ok(values) => values,
+        Err(error) => {
+            error!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if cli.output == OutputFormat::Zone {
+        error!("--output zone is only supported by rrset list");
+        return ExitCode::FAILURE;
+    }
+    match write_list(&mut io::stdout().lock(), cli.output, values) {
+        Ok(()) => ExitCode::SUCCESS,
+        // A consumer like `head` closing the pipe has seen all it wanted
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            error!("Failed to write the output: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Writes the values as JSON array or as JSON Lines.
+fn write_list(out: &mut impl Write, format: OutputFormat, values: Vec<Value>) -> io::Result<()> {
+    match format {
+        OutputFormat::Jsonl => values.iter().try_for_each(|value| writeln!(out, "{value}")),
+        _ => writeln!(out, "{}", Value::Array(values)),
+    }
+}
+
+// Reduces the objects to the given fields, all fields are kept without any.
+fn project(values: Vec<Value>, fields: &[String]) -> Result<Vec<Value>, String> {
+    if fields.is_empty() {
+        return Ok(values);
+    }
+    if let Some(Value::Object(first)) = values.first() {
+        if let Some(unknown) = fields.iter().find(|field| !first.contains_key(*field)) {
+            let available: Vec<&str> = first.keys().map(String::as_str).collect();
+            return Err(format!(
+                "Unknown field {unknown}, available fields are {}",
+                available.join(",")
+            ));
+        }
+    }
+    Ok(values
+        .into_iter()
+        .map(|value| match value {
+            Value::Object(mut object) => Value::Object(
+                fields
+                    .iter()
+                    .filter_map(|field| object.remove_entry(field))
+                    .collect::<Map<_, _>>(),
+            ),
+            value => value,
+        })
+        .collect())
+}
+
+/// Prints a single item as JSON.
+pub fn print<T: Serialize>(item: &T) -> ExitCode {
+    match serde_json::to_string(item) {
+        Ok(json) => {
+            println!("{json}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            error!("Failed to serialize the data: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{project, write_list};
+    use crate::cli::OutputFormat;
+    use serde_json::{json, Value};
+
+    fn fields(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    fn rrsets() -> Vec<Value> {
+        vec![
+            json!({"subname": "www", "type": "A", "ttl": 3600, "records": ["192.0.2.1"]}),
+            json!({"subname": "", "type": "MX", "ttl": 300, "records": ["10 mx.example.com."]}),
+        ]
+    }
+
+    #[test]
+    fn project_keeps_nested_values() {
+        let projected = project(rrsets(), &fields(&["type", "records"])).unwrap();
+        assert_eq!(
+            projected,
+            [
+                json!({"type": "A", "records": ["192.0.2.1"]}),
+                json!({"type": "MX", "records": ["10 mx.example.com."]}),
+            ]
+        );
+        let nested = vec![json!({"name": "t", "policies": {"default": {"perm_write": true}}})];
+        assert_eq!(
+            project(nested.clone(), &fields(&["policies"])).unwrap(),
+            [json!({"policies": {"default": {"perm_write": true}}})]
+        );
+        assert_eq!(project(nested.clone(), &[]).unwrap(), nested);
+    }
+
+    #[test]
+    fn project_rejects_unknown_fields() {
+        let error = project(rrsets(), &fields(&["subname", "name"])).unwrap_err();
+        assert_eq!(
+            error,
+            "Unknown field name, available fields are records,subname,ttl,type"
+        );
+        // Fields within nested objects cannot be selected
+        assert!(project(rrsets(), &fields(&["records.0"])).is_err());
+        assert!(project(vec![], &fields(&["name"])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn jsonl_one_object_per_line() {
+        let mut out = vec![];
+        let values = project(rrsets(), &fields(&["subname", "ttl"])).unwrap();
+        write_list(&mut out, OutputFormat::Jsonl, values.clone()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"subname\":\"www\",\"ttl\":3600}\n{\"subname\":\"\",\"ttl\":300}\n"
+        );
+        let mut out = vec![];
+        write_list(&mut out, OutputFormat::Json, values).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"subname\":\"www\",\"ttl\":3600},{\"subname\":\"\",\"ttl\":300}]\n"
+        );
+        let mut out = vec![];
+        write_list(&mut out, OutputFormat::Jsonl, vec![]).unwrap();
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the file holding the state of a zone repository
+const STATE_FILE: &str = ".desec-repo.json";
+
+/// The state of a directory holding one zone file per domain.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct State {
+    /// Glob patterns of the domains kept in the repository
+    pub domains: Vec<String>,
+    /// The touched timestamp of each domain at the last pull
+    pub touched: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Whether the directory has been initialized as zone repository.
+    pub fn exists(dir: &Path) -> bool {
+        dir.join(STATE_FILE).exists()
+    }
+
+    pub fn load(dir: &Path) -> Result<State, String> {
+        let path = dir.join(STATE_FILE);
+        let content = fs::read_to_string(&path).map_err(|error| {
+            format!(
+                "Failed to read {}, initialize the repository with repo init: {error}",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|error| format!("Failed to parse {}: {error}", path.display()))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let path = dir.join(STATE_FILE);
+        let json = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(&path, json + "\n")
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+    }
+}
+
+/// The path of the zone file of the domain.
+pub fn zone_path(dir: &Path, domain: &str) -> PathBuf {
+    dir.join(format!("{domain}.zone"))
+}
+
+/// Returns the domains of all zone files in the directory with their paths.
+pub fn zone_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|error| format!("Failed to read {}: {error}", dir.display()))?;
+    let mut files = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|error| format!("Failed to read {}: {error}", dir.display()))?
+            .path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "zone")
+        {
+            if let Some(domain) = path.file_stem().and_then(|stem| stem.to_str()) {
+                files.push((domain.to_string(), path.clone()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,138 @@
+//! Names of reverse DNS zones for IPv4 (`in-addr.arpa`) and IPv6 (`ip6.arpa`) addresses.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Returns the reverse name of an address without trailing dot,
+/// e.g. `2.2.0.192.in-addr.arpa` for `192.0.2.2`, or the nibbles for IPv6.
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Parses a full reverse name back into the address, None for names of partial networks.
+pub fn address(name: &str) -> Option<IpAddr> {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    if let Some(labels) = name.strip_suffix(".in-addr.arpa") {
+        let octets: Vec<u8> = labels
+            .split('.')
+            .rev()
+            .map(|label| label.parse().ok())
+            .collect::<Option<_>>()?;
+        let octets: [u8; 4] = octets.try_into().ok()?;
+        return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+    }
+    let labels = name.strip_suffix(".ip6.arpa")?;
+    let nibbles: Vec<u8> = labels
+        .split('.')
+        .rev()
+        .map(|label| match label.len() {
+            1 => u8::from_str_radix(label, 16).ok(),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    if nibbles.len() != 32 {
+        return None;
+    }
+    let mut octets = [0u8; 16];
+    for (octet, pair) in octets.iter_mut().zip(nibbles.chunks(2)) {
+        *octet = pair[0] << 4 | pair[1];
+    }
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+/// Whether the domain is a reverse zone.
+pub fn is_reverse_zone(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain.ends_with(".in-addr.arpa") || domain.ends_with(".ip6.arpa")
+}
+
+/// Returns the most specific of the zones containing the name and the subname within it.
+pub fn split<'a>(name: &str, zones: &'a [String]) -> Option<(&'a str, String)> {
+    zones
+        .iter()
+        .filter_map(|zone| {
+            let subname = name.strip_suffix(zone.as_str())?;
+            match subname {
+                "" => Some((zone.as_str(), String::new())),
+                _ => Some((zone.as_str(), subname.strip_suffix('.')?.to_string())),
+            }
+        })
+        .max_by_key(|(zone, _)| zone.len())
+}
+
+/// Returns the hostname as fully qualified name with trailing dot, as used in PTR records.
+pub fn fqdn(hostname: &str) -> String {
+    format!("{}.", hostname.trim_end_matches('.').to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_name_ipv4() {
+        let ip: IpAddr = "192.0.2.2".parse().unwrap();
+        assert_eq!(reverse_name(ip), "2.2.0.192.in-addr.arpa");
+        assert_eq!(address("2.2.0.192.in-addr.arpa."), Some(ip));
+    }
+
+    #[test]
+    fn reverse_name_ipv6() {
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        let name = "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa";
+        assert_eq!(reverse_name(ip), name);
+        assert_eq!(address(name), Some(ip));
+        assert_eq!(address(&name.to_ascii_uppercase()), Some(ip));
+    }
+
+    #[test]
+    fn address_of_partial_names() {
+        assert_eq!(address("2.0.192.in-addr.arpa"), None);
+        assert_eq!(address("256.2.0.192.in-addr.arpa"), None);
+        assert_eq!(address("8.b.d.0.1.0.0.2.ip6.arpa"), None);
+        assert_eq!(address("10.0.0.2.ip6.arpa"), None);
+        assert_eq!(address("www.example.com"), None);
+    }
+
+    #[test]
+    fn split_most_specific_zone() {
+        let zones = vec![
+            String::from("0.192.in-addr.arpa"),
+            String::from("2.0.192.in-addr.arpa"),
+        ];
+        assert_eq!(
+            split("2.2.0.192.in-addr.arpa", &zones),
+            Some(("2.0.192.in-addr.arpa", String::from("2")))
+        );
+        assert_eq!(
+            split("2.0.192.in-addr.arpa", &zones),
+            Some(("2.0.192.in-addr.arpa", String::new()))
+        );
+        assert_eq!(split("2.2.0.193.in-addr.arpa", &zones), None);
+        assert_eq!(
+            split("2.12.0.192.in-addr.arpa", &zones).map(|(zone, _)| zone),
+            Some("0.192.in-addr.arpa")
+        );
+    }
+
+    #[test]
+    fn reverse_zones_and_fqdn() {
+        assert!(is_reverse_zone("2.0.192.in-addr.arpa."));
+        assert!(is_reverse_zone("8.B.D.0.1.0.0.2.IP6.ARPA"));
+        assert!(!is_reverse_zone("example.com"));
+        assert_eq!(fqdn("Host.Example.com"), "host.example.com.");
+        assert_eq!(fqdn("host.example.com."), "host.example.com.");
+    }
+}
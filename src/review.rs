@@ -0,0 +1,83 @@
+//! Reviewing planned rrset changes before they are written, shared by all commands changing rrsets.
+
+use crate::cli::Cli;
+use crate::confirm::confirm;
+use crate::{dry_run, fail};
+use desec_cli::api::Client;
+use desec_cli::changeset::{self, RrsetChange};
+use desec_cli::command;
+use log::{error, info};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Shows the changes, asks for confirmation, saves an undo file if requested
+/// and applies the changes with one bulk request per domain.
+pub async fn apply_changes(
+    cli: &Cli,
+    client: &Client,
+    changes: &[RrsetChange],
+    undo_file: Option<PathBuf>,
+) -> ExitCode {
+    if let Some(exit_code) = review_changes(cli, changes) {
+        return exit_code;
+    }
+    let since = cli.if_unchanged_since.as_ref();
+    let results = match command::rrset::apply(client, changes, undo_file.as_deref(), since).await {
+        Ok(results) => results,
+        Err(error) => return fail(error),
+    };
+    let exit_code = report(results);
+    if let Some(path) = &undo_file {
+        info!(
+            "Revert the changes with: desec_cli rrset restore {}",
+            path.display()
+        );
+    }
+    exit_code
+}
+
+/// Prints the changes and asks for confirmation, in dry-run mode the requests are printed instead.
+/// Returns the exit code if the changes must not be written.
+pub fn review_changes(cli: &Cli, changes: &[RrsetChange]) -> Option<ExitCode> {
+    for change in changes {
+        print!("{change}");
+    }
+    let domains = changeset::by_domain(changes);
+    if cli.dry_run {
+        for (domain, rrsets) in &domains {
+            println!();
+            if dry_run::rrset_changes(domain, rrsets).print() == ExitCode::FAILURE {
+                return Some(ExitCode::FAILURE);
+            }
+        }
+        return Some(ExitCode::SUCCESS);
+    }
+    if !cli.yes
+        && !confirm(
+            &format!(
+                "change {} rrsets in {} domains",
+                changes.len(),
+                domains.len()
+            ),
+            "yes",
+        )
+    {
+        return Some(ExitCode::FAILURE);
+    }
+    None
+}
+
+/// Reports the result of writing the changes of each domain.
+pub fn report(results: Vec<(String, command::Result<usize>)>) -> ExitCode {
+    let mut exit_code = ExitCode::SUCCESS;
+    for (domain, result) in results {
+        match result {
+            Ok(count) => info!("Changed {count} rrsets of {domain}"),
+            Err(error) => {
+                error!("{error}");
+                exit_code = ExitCode::FAILURE;
+            }
+        }
+    }
+    exit_code
+}
@@ -0,0 +1,219 @@
+use crate::api::Client;
+use std::fmt;
+
+/// A single rrset given on the command line, either as domain, subname and type
+/// or as fully qualified name and type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RrsetName {
+    pub domain: String,
+    /// The subname as used by the API, empty for the zone apex
+    pub subname: String,
+    pub r#type: String,
+}
+
+impl RrsetName {
+    /// The subname as expected by desec_api, None for the zone apex.
+    pub fn subname(&self) -> Option<&str> {
+        subname(&self.subname)
+    }
+
+    /// The subname as used in rrset URLs, @ for the zone apex.
+    pub fn url_subname(&self) -> &str {
+        display_subname(&self.subname)
+    }
+}
+
+impl fmt::Display for RrsetName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.subname() {
+            Some(subname) => write!(f, "{} {subname}.{}", self.r#type, self.domain),
+            None => write!(f, "{} {}", self.r#type, self.domain),
+        }
+    }
+}
+
+/// Maps a subname given on the command line to the one used by desec_api,
+/// @ and the empty subname stand for the zone apex.
+pub fn subname(subname: &str) -> Option<&str> {
+    match subname {
+        "@" | "" => None,
+        subname => Some(subname),
+    }
+}
+
+/// Maps a subname used by the API to the one shown to users, @ for the zone apex.
+pub fn display_subname(subname: &str) -> &str {
+    match subname {
+        "" => "@",
+        subname => subname,
+    }
+}
+
+/// Resolves `<DOMAIN> <SUBNAME> <TYPE>` or `<FQDN> <TYPE>` to the rrset they name.
+pub async fn resolve(client: &Client, words: &[String]) -> Result<RrsetName, String> {
+    match words {
+        [domain, subname, r#type] => Ok(RrsetName {
+            domain: domain.clone(),
+            subname: self::subname(subname).unwrap_or_default().to_string(),
+            r#type: r#type.clone(),
+        }),
+        [fqdn, r#type] => {
+            let (domain, subname) = split_fqdn(client, fqdn).await?;
+            Ok(RrsetName {
+                domain,
+                subname,
+                r#type: r#type.clone(),
+            })
+        }
+        _ => Err(String::from(
+            "Expected either <DOMAIN> <SUBNAME> <TYPE> or <FQDN> <TYPE>",
+        )),
+    }
+}
+
+/// Resolves `<DOMAIN> <SUBNAME> <TYPE> <TTL> [RECORDS]...` or `<FQDN> <TYPE> <TTL> [RECORDS]...`
+/// to the rrset they name, its TTL and its records.
+///
+/// The fully qualified form is used if the third word is a TTL.
+pub async fn resolve_with_records(
+    client: &Client,
+    words: &[String],
+) -> Result<(RrsetName, u64, Vec<String>), String> {
+    let (name_words, ttl, records) = split_records(words)?;
+    let name = resolve(client, name_words).await?;
+    Ok((name, ttl, records.to_vec()))
+}
+
+// Splits the words into those naming the rrset, the TTL and the records.
+fn split_records(words: &[String]) -> Result<(&[String], u64, &[String]), String> {
+    let fqdn_form = words.get(2).is_some_and(|word| word.parse::<u64>().is_ok());
+    let (name_words, rest) = words.split_at(words.len().min(if fqdn_form { 2 } else { 3 }));
+    let Some((ttl, records)) = rest.split_first() else {
+        return Err(String::from(
+            "Expected either <DOMAIN> <SUBNAME> <TYPE> <TTL> or <FQDN> <TYPE> <TTL>",
+        ));
+    };
+    let ttl = ttl
+        .parse()
+        .map_err(|error| format!("Invalid TTL {ttl}: {error}"))?;
+    Ok((name_words, ttl, records))
+}
+
+/// Splits a fully qualified name into the domain of the account it belongs to and its subname.
+pub async fn split_fqdn(client: &Client, fqdn: &str) -> Result<(String, String), String> {
+    let fqdn = fqdn.trim_end_matches('.').to_ascii_lowercase();
+    let domains = client
+        .domain()
+        .get_owning_domain(covered_name(&fqdn))
+        .await
+        .map_err(|error| format!("Lookup of the domain owning {fqdn} failed: {error}"))?;
+    let owner = domains.first().map(|domain| domain.name.as_str());
+    split_owned(&fqdn, owner)
+}
+
+// Wildcard names are owned by the domain owning the name they cover.
+fn covered_name(fqdn: &str) -> &str {
+    let mut qname = fqdn;
+    while let Some(covered) = qname.strip_prefix("*.") {
+        qname = covered;
+    }
+    qname
+}
+
+// Splits the name into the domain owning it, if any, and its subname within it.
+fn split_owned(fqdn: &str, domain: Option<&str>) -> Result<(String, String), String> {
+    let Some(domain) = domain else {
+        return Err(format!("No domain of the account contains {fqdn}"));
+    };
+    let subname = match fqdn.strip_suffix(domain) {
+        Some("") => String::new(),
+        Some(prefix) => match prefix.strip_suffix('.') {
+            Some(subname) => subname.to_string(),
+            None => return Err(format!("{fqdn} is not part of the domain {domain}")),
+        },
+        None => return Err(format!("{fqdn} is not part of the domain {domain}")),
+    };
+    Ok((domain.to_string(), subname))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{covered_name, resolve, split_owned, split_records, RrsetName};
+    use crate::api::Client;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn resolve_domain_form() {
+        let client = Client::unauthenticated().unwrap();
+        let name = resolve(&client, &words(&["example.com", "@", "A"]))
+            .await
+            .unwrap();
+        let expected = RrsetName {
+            domain: String::from("example.com"),
+            subname: String::new(),
+            r#type: String::from("A"),
+        };
+        assert_eq!(name, expected);
+        assert_eq!(name.to_string(), "A example.com");
+        assert_eq!(name.url_subname(), "@");
+        let name = resolve(&client, &words(&["example.com", "*.sub", "TXT"]))
+            .await
+            .unwrap();
+        assert_eq!(name.subname(), Some("*.sub"));
+        assert_eq!(name.to_string(), "TXT *.sub.example.com");
+        assert!(resolve(&client, &words(&["example.com"])).await.is_err());
+    }
+
+    #[test]
+    fn split_records_forms() {
+        let domain_form = words(&["example.com", "www", "A", "300", "192.0.2.1", "192.0.2.2"]);
+        let (name, ttl, records) = split_records(&domain_form).unwrap();
+        assert_eq!(name, &domain_form[..3]);
+        assert_eq!(ttl, 300);
+        assert_eq!(records, &domain_form[4..]);
+        // A third word parsing as TTL selects the fully qualified form
+        let fqdn_form = words(&["www.example.com", "A", "300", "192.0.2.1"]);
+        let (name, ttl, records) = split_records(&fqdn_form).unwrap();
+        assert_eq!(name, &fqdn_form[..2]);
+        assert_eq!(ttl, 300);
+        assert_eq!(records, &fqdn_form[3..]);
+        let no_records = words(&["www.example.com", "A", "300"]);
+        assert!(split_records(&no_records).unwrap().2.is_empty());
+        assert!(split_records(&words(&["example.com", "www", "A"])).is_err());
+        assert!(split_records(&words(&["example.com", "www", "A", "soon"])).is_err());
+    }
+
+    #[test]
+    fn wildcards_owned_by_covered_name() {
+        assert_eq!(covered_name("*.sub.example.com"), "sub.example.com");
+        assert_eq!(covered_name("*.*.example.com"), "example.com");
+        assert_eq!(covered_name("www.example.com"), "www.example.com");
+    }
+
+    #[test]
+    fn split_owned_names() {
+        let split = |fqdn, domain| split_owned(fqdn, domain).map(|(_, subname)| subname);
+        assert_eq!(split("example.com", Some("example.com")).unwrap(), "");
+        assert_eq!(
+            split("www.sub.example.com", Some("example.com")).unwrap(),
+            "www.sub"
+        );
+        assert_eq!(
+            split("*.sub.example.com", Some("example.com")).unwrap(),
+            "*.sub"
+        );
+        assert_eq!(
+            split_owned("www.example.com", Some("example.com"))
+                .unwrap()
+                .0,
+            "example.com"
+        );
+        assert!(split("badexample.com", Some("example.com")).is_err());
+        // Names outside of the domains of the account have no owning domain
+        let error = split("www.example.net", None).unwrap_err();
+        assert_eq!(error, "No domain of the account contains www.example.net");
+    }
+}
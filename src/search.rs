@@ -0,0 +1,206 @@
+use crate::api::Client;
+use crate::rrset_name;
+use desec_api::rrset::ResourceRecordSet;
+use desec_api::Error;
+use futures::stream::{self, StreamExt};
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+
+/// Criteria an rrset has to meet to be part of a search result.
+/// Criteria which are not set match every rrset.
+#[derive(Default)]
+pub struct RrsetFilter {
+    /// Matches if any of the records matches
+    pub value: Option<Regex>,
+    /// Matches if the type is one of the given ones
+    pub types: Vec<String>,
+    /// Matches the subname ignoring case like DNS does, `@` stands for the zone apex
+    pub subname: Option<Pattern>,
+    pub min_ttl: Option<u64>,
+    pub max_ttl: Option<u64>,
+}
+
+impl RrsetFilter {
+    pub fn matches(&self, rrset: &ResourceRecordSet) -> bool {
+        if !self.types.is_empty()
+            && !self
+                .types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(&rrset.rrset_type))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.subname {
+            let options = MatchOptions {
+                case_sensitive: false,
+                ..Default::default()
+            };
+            if !pattern.matches_with(subname(rrset), options) {
+                return false;
+            }
+        }
+        if self.min_ttl.is_some_and(|min| rrset.ttl < min)
+            || self.max_ttl.is_some_and(|max| rrset.ttl > max)
+        {
+            return false;
+        }
+        match &self.value {
+            Some(regex) => rrset.records.iter().any(|record| regex.is_match(record)),
+            None => true,
+        }
+    }
+}
+
+/// Returns the subname of the rrset, using `@` for the zone apex.
+pub fn subname(rrset: &ResourceRecordSet) -> &str {
+    rrset_name::display_subname(rrset.subname.as_deref().unwrap_or_default())
+}
+
+/// Returns the names of all domains of the account matching the optional glob pattern.
+pub async fn domain_names(
+    client: &Client,
+    pattern: Option<&Pattern>,
+) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = client
+        .domain()
+        .get_domains()
+        .await?
+        .into_iter()
+        .map(|domain| domain.name)
+        .filter(|name| pattern.map_or(true, |pattern| pattern.matches(name)))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Fetches the rrsets of the given domains with at most `parallel` requests at a time
+/// and keeps those matching the filter.
+///
+/// Every request goes through the client, so its retry settings apply to throttled requests.
+/// The results are returned per domain in the order of the given domains.
+pub async fn search(
+    client: &Client,
+    domains: &[String],
+    filter: &RrsetFilter,
+    parallel: usize,
+) -> Vec<(String, Result<Vec<ResourceRecordSet>, Error>)> {
+    // A single type can be filtered by the API, which keeps the responses small
+    let server_type = match filter.types.as_slice() {
+        [r#type] => Some(r#type.as_str()),
+        _ => None,
+    };
+    let mut results: Vec<(usize, String, Result<Vec<ResourceRecordSet>, Error>)> =
+        stream::iter(domains.iter().enumerate())
+            .map(|(index, domain)| async move {
+                let rrsets = match server_type {
+                    Some(r#type) => client.rrset().get_rrsets_by_type(domain, r#type).await,
+                    None => client.rrset().get_rrsets(domain).await,
+                };
+                let rrsets = rrsets.map(|rrsets| {
+                    rrsets
+                        .into_iter()
+                        .filter(|rrset| filter.matches(rrset))
+                        .collect()
+                });
+                (index, domain.clone(), rrsets)
+            })
+            .buffer_unordered(parallel.max(1))
+            .collect()
+            .await;
+    results.sort_by_key(|(index, _, _)| *index);
+    results
+        .into_iter()
+        .map(|(_, domain, rrsets)| (domain, rrsets))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RrsetFilter;
+    use desec_api::rrset::ResourceRecordSet;
+    use glob::Pattern;
+    use regex::Regex;
+
+    fn rrset(subname: &str, r#type: &str, ttl: u64, records: &[&str]) -> ResourceRecordSet {
+        ResourceRecordSet {
+            created: String::new(),
+            domain: String::from("example.com"),
+            subname: Some(subname.to_string()),
+            name: String::new(),
+            rrset_type: r#type.to_string(),
+            ttl,
+            records: records.iter().map(|record| record.to_string()).collect(),
+            touched: String::new(),
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = RrsetFilter::default();
+        assert!(filter.matches(&rrset("", "A", 3600, &["192.0.2.1"])));
+        assert!(filter.matches(&rrset("www", "TXT", 60, &[])));
+    }
+
+    #[test]
+    fn matches_types_ignoring_case() {
+        let filter = RrsetFilter {
+            types: vec![String::from("a"), String::from("AAAA")],
+            ..Default::default()
+        };
+        assert!(filter.matches(&rrset("www", "A", 3600, &["192.0.2.1"])));
+        assert!(filter.matches(&rrset("www", "AAAA", 3600, &["2001:db8::1"])));
+        assert!(!filter.matches(&rrset("www", "CNAME", 3600, &["example.net."])));
+    }
+
+    #[test]
+    fn matches_subname_pattern() {
+        let filter = |pattern: &str| RrsetFilter {
+            subname: Some(Pattern::new(pattern).unwrap()),
+            ..Default::default()
+        };
+        let apex = rrset("", "MX", 3600, &["10 mx.example.com."]);
+        let mail = rrset("mail.eu", "A", 3600, &["192.0.2.1"]);
+        assert!(filter("@").matches(&apex));
+        assert!(!filter("@").matches(&mail));
+        assert!(filter("mail.*").matches(&mail));
+        assert!(filter("*.EU").matches(&mail));
+        assert!(!filter("www*").matches(&mail));
+        assert!(filter("[*]").matches(&rrset("*", "A", 3600, &["192.0.2.2"])));
+    }
+
+    #[test]
+    fn matches_any_record() {
+        let filter = |regex: &str| RrsetFilter {
+            value: Some(Regex::new(regex).unwrap()),
+            ..Default::default()
+        };
+        let mx = rrset(
+            "",
+            "MX",
+            3600,
+            &["10 mx1.example.net.", "20 mx2.example.net."],
+        );
+        assert!(filter(r"mx2\.example").matches(&mx));
+        assert!(!filter("^mx").matches(&mx));
+        // Record values are matched as given, case is ignored only if requested
+        assert!(!filter("MX2").matches(&mx));
+        assert!(filter("(?i)MX2").matches(&mx));
+        assert!(!filter("mx").matches(&rrset("", "MX", 3600, &[])));
+    }
+
+    #[test]
+    fn matches_all_criteria() {
+        let filter = RrsetFilter {
+            value: Some(Regex::new("192\\.0\\.2\\.").unwrap()),
+            types: vec![String::from("A")],
+            subname: Some(Pattern::new("www").unwrap()),
+            min_ttl: Some(300),
+            max_ttl: Some(3600),
+        };
+        assert!(filter.matches(&rrset("www", "A", 300, &["192.0.2.1"])));
+        assert!(!filter.matches(&rrset("www", "A", 60, &["192.0.2.1"])));
+        assert!(!filter.matches(&rrset("www", "A", 86400, &["192.0.2.1"])));
+        assert!(!filter.matches(&rrset("ftp", "A", 300, &["192.0.2.1"])));
+        assert!(!filter.matches(&rrset("www", "A", 300, &["198.51.100.1"])));
+    }
+}
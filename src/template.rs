@@ -0,0 +1,416 @@
+use crate::changeset::{RrsetChange, RrsetState};
+use crate::config::Config;
+use crate::rrset_name;
+use desec_api::rrset::ResourceRecordSet;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::{fs, io};
+
+/// Templates shipped with desec_cli, user templates with the same name take precedence.
+const BUILTIN: &[(&str, &str)] = &[
+    ("fastmail", include_str!("templates/fastmail.yaml")),
+    ("github-pages", include_str!("templates/github-pages.yaml")),
+    (
+        "google-workspace",
+        include_str!("templates/google-workspace.yaml"),
+    ),
+    ("mailgun", include_str!("templates/mailgun.yaml")),
+    (
+        "microsoft-365",
+        include_str!("templates/microsoft-365.yaml"),
+    ),
+];
+
+/// A bundle of rrsets for a common service setup.
+///
+/// Subnames and records may contain variables like `{{name}}`,
+/// `{{domain}}` always holds the domain the template is applied to.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    #[serde(default)]
+    pub description: String,
+    /// Variables of the template with their default, variables without default are required
+    #[serde(default)]
+    pub variables: BTreeMap<String, Option<String>>,
+    pub rrsets: Vec<TemplateRrset>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateRrset {
+    #[serde(default)]
+    pub subname: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    #[serde(default = "default_ttl")]
+    pub ttl: u64,
+    /// Whether the records replace existing ones instead of being added to them
+    #[serde(default)]
+    pub replace: bool,
+    pub records: Vec<String>,
+}
+
+fn default_ttl() -> u64 {
+    3600
+}
+
+/// Returns the names of all available templates with their description.
+pub fn list() -> BTreeMap<String, String> {
+    let mut templates = BTreeMap::new();
+    let builtin = BUILTIN.iter().map(|(name, _)| name.to_string());
+    let user = user_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            match path.extension()?.to_str()? {
+                "yaml" | "yml" => Some(path.file_stem()?.to_str()?.to_string()),
+                _ => None,
+            }
+        });
+    for name in builtin.chain(user) {
+        let description = load(&name)
+            .map(|template| template.description)
+            .unwrap_or_else(|error| error);
+        templates.insert(name, description);
+    }
+    templates
+}
+
+/// Loads the template with the given name from the config directory or the built-in templates.
+pub fn load(name: &str) -> Result<Template, String> {
+    for extension in ["yaml", "yml"] {
+        let Some(path) = user_dir().map(|dir| dir.join(format!("{name}.{extension}"))) else {
+            break;
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                return serde_yaml::from_str(&content)
+                    .map_err(|error| format!("Failed to parse {}: {error}", path.display()))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        }
+    }
+    match BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+        Some((_, content)) => serde_yaml::from_str(content)
+            .map_err(|error| format!("Failed to parse built-in template {name}: {error}")),
+        None => Err(format!("Unknown template {name}")),
+    }
+}
+
+fn user_dir() -> Option<std::path::PathBuf> {
+    Some(Config::dir()?.join("templates"))
+}
+
+impl Template {
+    /// Substitutes the variables and returns the resulting rrsets.
+    pub fn render(
+        &self,
+        domain: &str,
+        vars: &[(String, String)],
+    ) -> Result<Vec<TemplateRrset>, String> {
+        let mut values = BTreeMap::new();
+        values.insert(
+            String::from("domain"),
+            domain.trim_end_matches('.').to_string(),
+        );
+        for (name, default) in &self.variables {
+            if let Some(default) = default {
+                values.insert(name.clone(), default.clone());
+            }
+        }
+        for (name, value) in vars {
+            if !self.variables.contains_key(name) {
+                return Err(format!("The template has no variable {name}"));
+            }
+            values.insert(name.clone(), value.clone());
+        }
+        if let Some(name) = self
+            .variables
+            .keys()
+            .find(|name| !values.contains_key(*name))
+        {
+            return Err(format!(
+                "Missing value for variable {name}, pass --var {name}=<value>"
+            ));
+        }
+        let variable = Regex::new(r"\{\{\s*([A-Za-z0-9_-]+)\s*\}\}").expect("valid regex");
+        let substitute = |text: &str| -> Result<String, String> {
+            if let Some(name) = variable
+                .captures_iter(text)
+                .map(|captures| captures[1].to_string())
+                .find(|name| !values.contains_key(name))
+            {
+                return Err(format!("Undeclared variable {name} in the template"));
+            }
+            Ok(variable
+                .replace_all(text, |captures: &Captures| values[&captures[1]].clone())
+                .into_owned())
+        };
+        self.rrsets
+            .iter()
+            .map(|rrset| {
+                Ok(TemplateRrset {
+                    subname: substitute(&rrset.subname)?,
+                    records: rrset
+                        .records
+                        .iter()
+                        .map(|record| substitute(record))
+                        .collect::<Result<_, _>>()?,
+                    ..rrset.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// Computes the changes merging the rendered template rrsets into the existing rrsets of the domain.
+///
+/// Records are added to existing rrsets unless the template replaces them,
+/// a TXT record tagged like `v=spf1` replaces the existing record with the same tag.
+pub fn merge(
+    domain: &str,
+    rrsets: &[TemplateRrset],
+    existing: &[ResourceRecordSet],
+) -> Vec<RrsetChange> {
+    let mut changes: Vec<RrsetChange> = vec![];
+    for rrset in rrsets {
+        let subname = rrset_name::subname(&rrset.subname).unwrap_or_default();
+        let before = existing
+            .iter()
+            .map(RrsetState::from)
+            .find(|state| state.subname == subname && state.r#type == rrset.r#type);
+        let mut records = match (&before, rrset.replace) {
+            (Some(before), false) => before.records.clone(),
+            _ => vec![],
+        };
+        for record in &rrset.records {
+            if records.contains(record) {
+                continue;
+            }
+            if rrset.r#type == "TXT" {
+                if let Some(tag) = txt_tag(record) {
+                    records.retain(|existing| txt_tag(existing) != Some(tag));
+                }
+            }
+            records.push(record.clone());
+        }
+        let after = RrsetState {
+            subname: subname.to_string(),
+            r#type: rrset.r#type.clone(),
+            ttl: before.as_ref().map_or(rrset.ttl, |before| before.ttl),
+            records,
+        };
+        if before.as_ref() != Some(&after) {
+            changes.push(RrsetChange {
+                domain: domain.to_string(),
+                before,
+                after,
+            });
+        }
+    }
+    changes
+}
+
+// The version tag of TXT records like SPF or DMARC, of which only one may exist per name.
+fn txt_tag(record: &str) -> Option<&str> {
+    let content = record.trim_start_matches('"');
+    let tag = content.split([' ', ';', '"']).next()?;
+    match tag.to_ascii_lowercase().starts_with("v=") {
+        true => Some(tag),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, Template, TemplateRrset, BUILTIN};
+    use desec_api::rrset::ResourceRecordSet;
+
+    fn template(yaml: &str) -> Template {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn existing(subname: &str, r#type: &str, records: &[&str]) -> ResourceRecordSet {
+        ResourceRecordSet {
+            created: String::new(),
+            domain: String::from("example.com"),
+            subname: Some(subname.to_string()),
+            name: String::new(),
+            rrset_type: r#type.to_string(),
+            ttl: 300,
+            records: records.iter().map(|record| record.to_string()).collect(),
+            touched: String::new(),
+        }
+    }
+
+    #[test]
+    fn builtin_templates_render() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/templates");
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        let bundled: Vec<String> = BUILTIN
+            .iter()
+            .map(|(name, _)| format!("{name}.yaml"))
+            .collect();
+        assert_eq!(files, bundled);
+        for (name, content) in BUILTIN {
+            let template: Template = serde_yaml::from_str(content)
+                .unwrap_or_else(|error| panic!("{name} does not parse: {error}"));
+            assert!(
+                !template.description.is_empty(),
+                "{name} has no description"
+            );
+            let required: Vec<(String, String)> = template
+                .variables
+                .iter()
+                .filter(|(_, default)| default.is_none())
+                .map(|(variable, _)| (variable.clone(), String::from("value")))
+                .collect();
+            let rrsets = template
+                .render("example.com", &required)
+                .unwrap_or_else(|error| panic!("{name} does not render: {error}"));
+            assert!(!rrsets.is_empty(), "{name} has no rrsets");
+            for rrset in &rrsets {
+                assert!(!rrset.subname.contains("{{"), "{name}: {}", rrset.subname);
+                assert!(rrset.records.iter().all(|record| !record.contains("{{")));
+            }
+        }
+    }
+
+    #[test]
+    fn render_substitutes_variables() {
+        let template = template(
+            "
+variables:
+  selector: mail
+  target:
+rrsets:
+  - subname: '{{ selector }}._domainkey'
+    type: CNAME
+    records: ['{{target}}.{{domain}}.']
+",
+        );
+        let rrsets = template
+            .render("example.com.", &vars(&[("target", "dkim")]))
+            .unwrap();
+        assert_eq!(rrsets[0].subname, "mail._domainkey");
+        assert_eq!(rrsets[0].records, ["dkim.example.com."]);
+        assert_eq!(rrsets[0].ttl, 3600);
+        let rrsets = template
+            .render("example.com", &vars(&[("target", "x"), ("selector", "s1")]))
+            .unwrap();
+        assert_eq!(rrsets[0].subname, "s1._domainkey");
+    }
+
+    #[test]
+    fn render_errors() {
+        let template = template(
+            "
+variables:
+  target:
+rrsets:
+  - type: CNAME
+    records: ['{{target}}.{{other}}.']
+",
+        );
+        assert_eq!(
+            template.render("example.com", &[]).unwrap_err(),
+            "Missing value for variable target, pass --var target=<value>"
+        );
+        assert_eq!(
+            template
+                .render("example.com", &vars(&[("target", "x"), ("unknown", "y")]))
+                .unwrap_err(),
+            "The template has no variable unknown"
+        );
+        assert_eq!(
+            template
+                .render("example.com", &vars(&[("target", "x")]))
+                .unwrap_err(),
+            "Undeclared variable other in the template"
+        );
+    }
+
+    fn rrset(subname: &str, r#type: &str, replace: bool, records: &[&str]) -> TemplateRrset {
+        TemplateRrset {
+            subname: subname.to_string(),
+            r#type: r#type.to_string(),
+            ttl: 3600,
+            replace,
+            records: records.iter().map(|record| record.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn merge_replaces_tagged_txt_records() {
+        let existing = [existing(
+            "",
+            "TXT",
+            &["\"v=spf1 mx -all\"", "\"google-site-verification=abc\""],
+        )];
+        let template = [rrset(
+            "@",
+            "TXT",
+            false,
+            &["\"v=spf1 include:example.net -all\""],
+        )];
+        let changes = merge("example.com", &template, &existing);
+        assert_eq!(changes.len(), 1);
+        let after = &changes[0].after;
+        assert_eq!(after.subname, "");
+        // The TTL of the existing rrset is kept
+        assert_eq!(after.ttl, 300);
+        assert_eq!(
+            after.records,
+            [
+                "\"google-site-verification=abc\"",
+                "\"v=spf1 include:example.net -all\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_adds_replaces_and_skips() {
+        let existing = [
+            existing("", "MX", &["10 old.example.net."]),
+            existing("www", "CNAME", &["example.net."]),
+        ];
+        let template = [
+            rrset("", "MX", false, &["20 new.example.net."]),
+            rrset("www", "CNAME", true, &["example.org."]),
+            rrset("new", "A", false, &["192.0.2.1"]),
+        ];
+        let changes = merge("example.com", &template, &existing);
+        let records: Vec<&[String]> = changes
+            .iter()
+            .map(|change| change.after.records.as_slice())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                &["10 old.example.net.", "20 new.example.net."][..],
+                &["example.org."][..],
+                &["192.0.2.1"][..],
+            ]
+        );
+        assert!(changes[2].before.is_none());
+        assert_eq!(changes[2].after.ttl, 3600);
+        // Applying the template again changes nothing
+        let template = [rrset("", "MX", false, &["10 old.example.net."])];
+        assert!(merge("example.com", &template, &existing).is_empty());
+    }
+}
@@ -0,0 +1,538 @@
+//! Interactive terminal UI for browsing and editing the zones of the account.
+//!
+//! Edits are only staged as pending changes, which are checked for concurrent changes
+//! and written with one bulk request per domain when they are committed.
+
+mod ui;
+
+use crate::cli::Cli;
+use crate::logging;
+use desec_cli::api::Client;
+use desec_cli::changeset::{RrsetChange, RrsetState};
+use desec_cli::command;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::widgets::{ListState, TableState};
+use ratatui::DefaultTerminal;
+use std::collections::HashMap;
+use std::io;
+
+/// The pane keys are sent to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Domains,
+    Rrsets,
+    Pending,
+}
+
+enum Mode {
+    Normal,
+    /// Typing the filter of the focused pane
+    Filter,
+    Input {
+        purpose: Input,
+        text: String,
+    },
+    Confirm {
+        question: String,
+        action: Action,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum Input {
+    Ttl,
+    Records,
+    Add,
+}
+
+enum Action {
+    Add(RrsetState),
+    Delete(RrsetState),
+    Commit,
+    Quit,
+}
+
+/// How an rrset shown in the rrset pane differs from the one on the server.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Unchanged,
+    Added,
+    Changed,
+    Deleted,
+}
+
+struct App {
+    domains: Vec<String>,
+    /// Rrsets of the domains as they are on the server, loaded when a domain is selected
+    rrsets: HashMap<String, Vec<RrsetState>>,
+    /// Changes which have not been written yet, at most one per rrset
+    pending: Vec<RrsetChange>,
+    focus: Focus,
+    mode: Mode,
+    domain_filter: String,
+    rrset_filter: String,
+    domain_list: ListState,
+    rrset_table: TableState,
+    pending_list: ListState,
+    status: String,
+}
+
+/// Runs the UI until the user quits.
+/// Returns the pending changes if they should be printed as dry run instead of being written.
+pub async fn run(cli: &Cli, client: &Client) -> command::Result<Vec<RrsetChange>> {
+    let domains = command::domain::list(client)
+        .await?
+        .into_iter()
+        .map(|domain| domain.name)
+        .collect();
+    let mut app = App::new(domains);
+    // Logged messages would be written over the UI, errors are shown in the status line instead
+    let paused = logging::pause();
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal, cli, client).await;
+    ratatui::restore();
+    drop(paused);
+    result
+        .map_err(|error| command::Error::Io(format!("Failed to run the terminal UI: {error}")))?;
+    match cli.dry_run {
+        true => Ok(app.pending),
+        false => Ok(vec![]),
+    }
+}
+
+impl App {
+    fn new(domains: Vec<String>) -> Self {
+        App {
+            domains,
+            rrsets: HashMap::new(),
+            pending: vec![],
+            focus: Focus::Domains,
+            mode: Mode::Normal,
+            domain_filter: String::new(),
+            rrset_filter: String::new(),
+            domain_list: ListState::default().with_selected(Some(0)),
+            rrset_table: TableState::default().with_selected(Some(0)),
+            pending_list: ListState::default(),
+            status: String::new(),
+        }
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        cli: &Cli,
+        client: &Client,
+    ) -> io::Result<()> {
+        loop {
+            // The rrsets are loaded after drawing, so the selection moves before the request
+            terminal.draw(|frame| ui::draw(frame, self))?;
+            if let Some(domain) = self.selected_domain() {
+                if !self.rrsets.contains_key(&domain) {
+                    self.load(client, &domain).await;
+                    continue;
+                }
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+            match mode {
+                Mode::Normal => {
+                    if self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+                Mode::Filter => self.handle_filter_key(key),
+                Mode::Input { purpose, text } => self.handle_input_key(key, purpose, text),
+                Mode::Confirm { action, .. } if key.code == KeyCode::Char('y') => match action {
+                    Action::Add(rrset) | Action::Delete(rrset) => self.stage(rrset),
+                    Action::Commit if cli.dry_run => return Ok(()),
+                    Action::Commit => {
+                        terminal.draw(|frame| ui::draw(frame, self))?;
+                        self.commit(cli, client).await;
+                    }
+                    Action::Quit => {
+                        self.pending.clear();
+                        return Ok(());
+                    }
+                },
+                Mode::Confirm { .. } => self.status = "Cancelled".to_string(),
+            }
+        }
+    }
+
+    /// Handles a key of the normal mode, returns whether to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => match self.pending.is_empty() {
+                true => return true,
+                false => self.confirm(
+                    format!("Discard {} pending changes and quit?", self.pending.len()),
+                    Action::Quit,
+                ),
+            },
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Domains => Focus::Rrsets,
+                    Focus::Rrsets if !self.pending.is_empty() => {
+                        self.pending_list.select(Some(0));
+                        Focus::Pending
+                    }
+                    Focus::Rrsets | Focus::Pending => Focus::Domains,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Char('/') if self.focus != Focus::Pending => self.mode = Mode::Filter,
+            KeyCode::Char('t') => self.input(Input::Ttl),
+            KeyCode::Char('e') | KeyCode::Enter => self.input(Input::Records),
+            KeyCode::Char('a') => self.input(Input::Add),
+            KeyCode::Char('d') | KeyCode::Delete => self.delete(),
+            KeyCode::Char('r') => {
+                if let Some(domain) = self.selected_domain() {
+                    // Drops the loaded rrsets, so they are loaded again before the next key
+                    self.rrsets.remove(&domain);
+                }
+            }
+            KeyCode::Char('c') => match self.pending.is_empty() {
+                true => self.status = "No pending changes".to_string(),
+                false => self.confirm(
+                    format!("Write {} pending changes?", self.pending.len()),
+                    Action::Commit,
+                ),
+            },
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        let filter = match self.focus {
+            Focus::Domains => &mut self.domain_filter,
+            _ => &mut self.rrset_filter,
+        };
+        match key.code {
+            KeyCode::Enter => return,
+            KeyCode::Esc => {
+                filter.clear();
+                return;
+            }
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Char(c) => filter.push(c),
+            _ => {}
+        }
+        match self.focus {
+            Focus::Domains => self.domain_list.select(Some(0)),
+            _ => self.rrset_table.select(Some(0)),
+        }
+        self.mode = Mode::Filter;
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent, purpose: Input, mut text: String) {
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                if let Err(error) = self.apply_input(purpose, &text) {
+                    self.status = error;
+                }
+                return;
+            }
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) => text.push(c),
+            _ => {}
+        }
+        self.mode = Mode::Input { purpose, text };
+    }
+
+    /// Starts editing the selected rrset or adding one to the selected domain.
+    fn input(&mut self, purpose: Input) {
+        let text = match (purpose, self.selected_rrset()) {
+            (Input::Add, _) if self.selected_domain().is_some() => String::new(),
+            (Input::Ttl, Some((_, rrset))) => rrset.ttl.to_string(),
+            (Input::Records, Some((_, rrset))) => rrset.records.join(" | "),
+            _ => return,
+        };
+        self.mode = Mode::Input { purpose, text };
+    }
+
+    fn apply_input(&mut self, purpose: Input, text: &str) -> Result<(), String> {
+        if let Input::Add = purpose {
+            let rrset = parse_rrset(text)?;
+            if self
+                .shown_rrsets()
+                .iter()
+                .any(|(_, shown)| same_name(shown, &rrset))
+            {
+                return Err(format!(
+                    "{} {} exists already, edit it instead",
+                    rrset.r#type,
+                    name(&rrset)
+                ));
+            }
+            self.confirm(
+                format!("Add {} {}?", rrset.r#type, name(&rrset)),
+                Action::Add(rrset),
+            );
+            return Ok(());
+        }
+        let Some((_, mut rrset)) = self.selected_rrset() else {
+            return Ok(());
+        };
+        match purpose {
+            Input::Ttl => {
+                rrset.ttl = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid TTL {text}"))?;
+            }
+            _ => {
+                rrset.records = parse_records(text);
+                if rrset.records.is_empty() {
+                    return Err("An rrset needs records, press d to delete it".to_string());
+                }
+            }
+        }
+        self.stage(rrset);
+        Ok(())
+    }
+
+    fn delete(&mut self) {
+        match self.focus {
+            Focus::Pending => {
+                if let Some(index) = self.pending_list.selected() {
+                    if index < self.pending.len() {
+                        self.pending.remove(index);
+                    }
+                }
+                match self.pending.is_empty() {
+                    true => self.focus = Focus::Rrsets,
+                    false => self.move_selection(0),
+                }
+            }
+            _ => match self.selected_rrset() {
+                Some((Marker::Deleted, _)) | None => {}
+                Some((_, rrset)) => self.confirm(
+                    format!("Delete {} {}?", rrset.r#type, name(&rrset)),
+                    Action::Delete(rrset.deleted()),
+                ),
+            },
+        }
+    }
+
+    fn confirm(&mut self, question: String, action: Action) {
+        self.mode = Mode::Confirm { question, action };
+    }
+
+    /// Adds the new state of an rrset of the selected domain to the pending changes,
+    /// replacing an earlier change of the same rrset.
+    fn stage(&mut self, after: RrsetState) {
+        let Some(domain) = self.selected_domain() else {
+            return;
+        };
+        let before = self.rrsets.get(&domain).and_then(|rrsets| {
+            rrsets
+                .iter()
+                .find(|rrset| same_name(rrset, &after))
+                .cloned()
+        });
+        self.pending
+            .retain(|change| change.domain != domain || !same_name(&change.after, &after));
+        let unchanged = match &before {
+            Some(before) => *before == after,
+            None => after.records.is_empty(),
+        };
+        if !unchanged {
+            self.pending.push(RrsetChange {
+                domain,
+                before,
+                after,
+            });
+        }
+    }
+
+    /// Writes the pending changes with one bulk request per domain, the changes of
+    /// domains which failed are kept.
+    async fn commit(&mut self, cli: &Cli, client: &Client) {
+        let results = match command::rrset::sync(
+            client,
+            &self.pending,
+            cli.if_unchanged_since.as_ref(),
+        )
+        .await
+        {
+            Ok(results) => results,
+            Err(error) => {
+                self.status = error.to_string();
+                return;
+            }
+        };
+        let mut errors = vec![];
+        for (domain, result) in results {
+            match result {
+                Ok(_) => {
+                    self.pending.retain(|change| change.domain != domain);
+                    self.rrsets.remove(&domain);
+                }
+                Err(error) => errors.push(error.to_string()),
+            }
+        }
+        self.status = match errors.is_empty() {
+            true => "All changes have been written".to_string(),
+            false => errors.join(", "),
+        };
+        if self.pending.is_empty() && self.focus == Focus::Pending {
+            self.focus = Focus::Rrsets;
+        }
+    }
+
+    async fn load(&mut self, client: &Client, domain: &str) {
+        let rrsets = match command::rrsets(client, domain).await {
+            Ok(rrsets) => rrsets.iter().map(RrsetState::from).collect(),
+            Err(error) => {
+                self.status = error.to_string();
+                vec![]
+            }
+        };
+        self.rrsets.insert(domain.to_string(), rrsets);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = match self.focus {
+            Focus::Domains => self.shown_domains().len(),
+            Focus::Rrsets => self.shown_rrsets().len(),
+            Focus::Pending => self.pending.len(),
+        };
+        let state = match self.focus {
+            Focus::Domains => {
+                self.rrset_table.select(Some(0));
+                self.domain_list.selected_mut()
+            }
+            Focus::Rrsets => self.rrset_table.selected_mut(),
+            Focus::Pending => self.pending_list.selected_mut(),
+        };
+        let selected = state.unwrap_or(0).saturating_add_signed(delta);
+        *state = Some(selected.min(len.saturating_sub(1)));
+    }
+
+    fn shown_domains(&self) -> Vec<&String> {
+        self.domains
+            .iter()
+            .filter(|domain| domain.contains(&self.domain_filter))
+            .collect()
+    }
+
+    fn selected_domain(&self) -> Option<String> {
+        let domains = self.shown_domains();
+        let index = self.domain_list.selected()?;
+        domains.get(index).map(|domain| domain.to_string())
+    }
+
+    /// The rrsets of the selected domain with the pending changes applied, filtered and sorted.
+    fn shown_rrsets(&self) -> Vec<(Marker, RrsetState)> {
+        let Some(domain) = self.selected_domain() else {
+            return vec![];
+        };
+        let pending: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|change| change.domain == domain)
+            .collect();
+        let mut shown: Vec<_> = self
+            .rrsets
+            .get(&domain)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|rrset| {
+                match pending
+                    .iter()
+                    .find(|change| same_name(&change.after, rrset))
+                {
+                    Some(change) if change.after.records.is_empty() => {
+                        (Marker::Deleted, rrset.clone())
+                    }
+                    Some(change) => (Marker::Changed, change.after.clone()),
+                    None => (Marker::Unchanged, rrset.clone()),
+                }
+            })
+            .collect();
+        for change in pending.iter().filter(|change| change.before.is_none()) {
+            shown.push((Marker::Added, change.after.clone()));
+        }
+        let filter = self.rrset_filter.to_lowercase();
+        shown.retain(|(_, rrset)| {
+            format!(
+                "{} {} {}",
+                name(rrset),
+                rrset.r#type,
+                rrset.records.join(" ")
+            )
+            .to_lowercase()
+            .contains(&filter)
+        });
+        shown.sort_by(|(_, a), (_, b)| (&a.subname, &a.r#type).cmp(&(&b.subname, &b.r#type)));
+        shown
+    }
+
+    fn selected_rrset(&self) -> Option<(Marker, RrsetState)> {
+        if self.focus != Focus::Rrsets {
+            return None;
+        }
+        let index = self.rrset_table.selected()?;
+        self.shown_rrsets().into_iter().nth(index)
+    }
+}
+
+fn same_name(a: &RrsetState, b: &RrsetState) -> bool {
+    a.subname == b.subname && a.r#type == b.r#type
+}
+
+fn name(rrset: &RrsetState) -> &str {
+    desec_cli::rrset_name::display_subname(&rrset.subname)
+}
+
+/// Splits the records of the input, which are separated by `|`.
+fn parse_records(text: &str) -> Vec<String> {
+    text.split('|')
+        .map(str::trim)
+        .filter(|record| !record.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses a new rrset given as `<SUBNAME> <TYPE> <TTL> <RECORD> | <RECORD>...`.
+fn parse_rrset(text: &str) -> Result<RrsetState, String> {
+    let error = || "Expected <subname> <type> <ttl> <record> | <record>...".to_string();
+    let (subname, rest) = text
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(error)?;
+    let (r#type, rest) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(error)?;
+    let (ttl, records) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(error)?;
+    let records = parse_records(records);
+    if records.is_empty() {
+        return Err(error());
+    }
+    Ok(RrsetState {
+        subname: desec_cli::rrset_name::subname(subname)
+            .unwrap_or_default()
+            .to_string(),
+        r#type: r#type.to_uppercase(),
+        ttl: ttl.parse().map_err(|_| format!("Invalid TTL {ttl}"))?,
+        records,
+    })
+}
@@ -0,0 +1,135 @@
+use super::{App, Focus, Input, Marker, Mode};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, List, Paragraph, Row, Table};
+use ratatui::Frame;
+
+const HELP: &str =
+    "tab pane  / filter  e records  t ttl  a add  d delete  r reload  c commit  q quit";
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let pending_height = match app.pending.is_empty() {
+        true => 0,
+        false => app.pending.len().min(8) as u16 + 2,
+    };
+    let [main, pending, status] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(pending_height),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [domains, rrsets] =
+        Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+
+    let items: Vec<String> = app.shown_domains().into_iter().cloned().collect();
+    let list = List::new(items)
+        .block(block(
+            "Domains",
+            &app.domain_filter,
+            app.focus == Focus::Domains,
+        ))
+        .highlight_style(highlight(app.focus == Focus::Domains));
+    frame.render_stateful_widget(list, domains, &mut app.domain_list);
+
+    let rows: Vec<Row> = app
+        .shown_rrsets()
+        .into_iter()
+        .map(|(marker, rrset)| {
+            let (symbol, color) = match marker {
+                Marker::Unchanged => (" ", Color::Reset),
+                Marker::Added => ("+", Color::Green),
+                Marker::Changed => ("~", Color::Yellow),
+                Marker::Deleted => ("-", Color::Red),
+            };
+            Row::new(vec![
+                symbol.to_string(),
+                super::name(&rrset).to_string(),
+                rrset.r#type.clone(),
+                rrset.ttl.to_string(),
+                rrset.records.join(" | "),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(1),
+        Constraint::Percentage(25),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Fill(1),
+    ];
+    let title = match app.selected_domain() {
+        Some(domain) => format!("Rrsets of {domain}"),
+        None => "Rrsets".to_string(),
+    };
+    let table = Table::new(rows, widths)
+        .header(Row::new(["", "Subname", "Type", "TTL", "Records"]).style(bold()))
+        .block(block(&title, &app.rrset_filter, app.focus == Focus::Rrsets))
+        .row_highlight_style(highlight(app.focus == Focus::Rrsets));
+    frame.render_stateful_widget(table, rrsets, &mut app.rrset_table);
+
+    if !app.pending.is_empty() {
+        let items: Vec<String> = app
+            .pending
+            .iter()
+            .map(|change| {
+                change
+                    .to_string()
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block("Pending changes", "", app.focus == Focus::Pending))
+            .highlight_style(highlight(app.focus == Focus::Pending));
+        frame.render_stateful_widget(list, pending, &mut app.pending_list);
+    }
+
+    let line = match &app.mode {
+        Mode::Normal if app.status.is_empty() => HELP.to_string(),
+        Mode::Normal => app.status.clone(),
+        Mode::Filter => format!("Filter: {}_", current_filter(app)),
+        Mode::Input { purpose, text } => {
+            let prompt = match purpose {
+                Input::Ttl => "TTL",
+                Input::Records => "Records separated by |",
+                Input::Add => "New rrset <subname> <type> <ttl> <record> | <record>...",
+            };
+            format!("{prompt}: {text}_")
+        }
+        Mode::Confirm { question, .. } => format!("{question} (y/n)"),
+    };
+    frame.render_widget(Paragraph::new(line), status);
+}
+
+fn current_filter(app: &App) -> &str {
+    match app.focus {
+        Focus::Domains => &app.domain_filter,
+        _ => &app.rrset_filter,
+    }
+}
+
+fn block<'a>(title: &str, filter: &str, focused: bool) -> Block<'a> {
+    let title = match filter.is_empty() {
+        true => format!(" {title} "),
+        false => format!(" {title} /{filter} "),
+    };
+    let block = Block::bordered().title(title);
+    match focused {
+        true => block.border_style(Style::default().fg(Color::Cyan)),
+        false => block,
+    }
+}
+
+fn highlight(focused: bool) -> Style {
+    match focused {
+        true => Style::default().add_modifier(Modifier::REVERSED),
+        false => Style::default().add_modifier(Modifier::BOLD),
+    }
+}
+
+fn bold() -> Style {
+    Style::default().add_modifier(Modifier::BOLD)
+}
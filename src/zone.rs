@@ -0,0 +1,405 @@
+use crate::changeset::RrsetState;
+use desec_api::domain::DNSSECKeyInfo;
+use desec_api::rrset::ResourceRecordSet;
+
+/// Types published by deSEC for DNSSEC which are not managed as rrsets
+pub const DNSSEC_TYPES: [&str; 3] = ["CDNSKEY", "CDS", "DNSKEY"];
+
+/// How a zone file is rendered.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZoneOptions {
+    /// Use fully qualified owner names instead of names relative to `$ORIGIN`
+    pub absolute: bool,
+}
+
+// A single record line of the zone file.
+struct Line {
+    name: String,
+    ttl: u64,
+    r#type: String,
+    data: String,
+}
+
+/// Renders the rrsets of a domain in RFC 1035 format.
+///
+/// Records are sorted by name and type and the columns are aligned, so that
+/// the same rrsets always result in the same file and exports can be diffed.
+/// DNSSEC records are only included for the given keys.
+pub fn render(
+    domain: &str,
+    rrsets: &[ResourceRecordSet],
+    keys: &[DNSSECKeyInfo],
+    options: ZoneOptions,
+) -> String {
+    let domain = domain.trim_end_matches('.');
+    let mut rrsets: Vec<&ResourceRecordSet> = rrsets
+        .iter()
+        .filter(|rrset| !DNSSEC_TYPES.contains(&rrset.rrset_type.as_str()))
+        .collect();
+    rrsets.sort_by_cached_key(|rrset| {
+        (
+            canonical_key(rrset.subname.as_deref().unwrap_or_default()),
+            rrset.rrset_type.clone(),
+        )
+    });
+    let owner = |subname: &str| match (subname, options.absolute) {
+        ("", true) => format!("{domain}."),
+        ("", false) => String::from("@"),
+        (subname, true) => format!("{subname}.{domain}."),
+        (subname, false) => subname.to_string(),
+    };
+    let ttl = rrsets
+        .iter()
+        .find(|rrset| rrset.subname.as_deref().unwrap_or_default().is_empty())
+        .map_or(3600, |rrset| rrset.ttl);
+    let mut lines: Vec<Line> = vec![];
+    for (r#type, data) in dnssec_records(keys) {
+        lines.push(Line {
+            name: owner(""),
+            ttl,
+            r#type: r#type.to_string(),
+            data,
+        });
+    }
+    for rrset in rrsets {
+        let mut records = rrset.records.clone();
+        records.sort();
+        for data in records {
+            lines.push(Line {
+                name: owner(rrset.subname.as_deref().unwrap_or_default()),
+                ttl: rrset.ttl,
+                r#type: rrset.rrset_type.clone(),
+                data,
+            });
+        }
+    }
+    let name_width = lines.iter().map(|line| line.name.len()).max().unwrap_or(0);
+    let ttl_width = lines
+        .iter()
+        .map(|line| line.ttl.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let type_width = lines
+        .iter()
+        .map(|line| line.r#type.len())
+        .max()
+        .unwrap_or(0);
+    let mut zone = String::new();
+    if !options.absolute {
+        zone.push_str(&format!("$ORIGIN {domain}.\n"));
+    }
+    for line in lines {
+        zone.push_str(&format!(
+            "{:<name_width$} {:>ttl_width$} IN {:<type_width$} {}\n",
+            line.name, line.ttl, line.r#type, line.data
+        ));
+    }
+    zone
+}
+
+// The DNSKEY, CDNSKEY and CDS records deSEC publishes at the apex for the keys.
+fn dnssec_records(keys: &[DNSSECKeyInfo]) -> Vec<(&'static str, String)> {
+    let mut records = vec![];
+    for key in keys {
+        records.push(("CDNSKEY", key.dnskey.clone()));
+        records.extend(key.ds.iter().map(|ds| ("CDS", ds.clone())));
+        records.push(("DNSKEY", key.dnskey.clone()));
+    }
+    records.sort();
+    records.dedup();
+    records
+}
+
+// Sorts names like DNSSEC does, i.e. by their labels from right to left, the apex first.
+fn canonical_key(subname: &str) -> Vec<String> {
+    match subname {
+        "" => vec![],
+        subname => subname
+            .rsplit('.')
+            .map(|label| label.to_ascii_lowercase())
+            .collect(),
+    }
+}
+
+/// Parses a zone file of the domain as written by [`render`] into rrsets.
+///
+/// Only one record per line is supported, a line starting with whitespace has the owner
+/// of the previous record. DNSSEC records are skipped as deSEC manages them.
+pub fn parse(domain: &str, zone: &str) -> Result<Vec<RrsetState>, String> {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let mut origin = format!("{domain}.");
+    let mut rrsets: Vec<RrsetState> = vec![];
+    let mut previous: Option<String> = None;
+    for (number, line) in zone.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", number + 1);
+        let mut line = strip_comment(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let name = match line.starts_with(char::is_whitespace) {
+            true => None,
+            false => next_field(&mut line),
+        };
+        if name.is_some_and(|name| name.eq_ignore_ascii_case("$ORIGIN")) {
+            origin = next_field(&mut line)
+                .ok_or_else(|| error("$ORIGIN without name"))?
+                .to_ascii_lowercase();
+            continue;
+        }
+        if let Some(name) = name.filter(|name| name.starts_with('$')) {
+            return Err(error(&format!("Unsupported directive {name}")));
+        }
+        if find_unquoted(line, '(').is_some() {
+            return Err(error("Records spanning multiple lines are not supported"));
+        }
+        let ttl: u64 = next_field(&mut line)
+            .and_then(|ttl| ttl.parse().ok())
+            .ok_or_else(|| error("Expected <NAME> <TTL> IN <TYPE> <DATA>"))?;
+        if !next_field(&mut line).is_some_and(|class| class.eq_ignore_ascii_case("IN")) {
+            return Err(error("Expected <NAME> <TTL> IN <TYPE> <DATA>"));
+        }
+        let r#type = next_field(&mut line)
+            .ok_or_else(|| error("Missing record type"))?
+            .to_ascii_uppercase();
+        // The data is kept verbatim, as whitespace within quoted strings matters
+        let data = line.trim().to_string();
+        if data.is_empty() {
+            return Err(error("Missing record data"));
+        }
+        let subname = match name {
+            Some(name) => subname(&domain, &origin, name).map_err(|message| error(&message))?,
+            None => previous
+                .clone()
+                .ok_or_else(|| error("Missing owner name of the first record"))?,
+        };
+        previous = Some(subname.clone());
+        if DNSSEC_TYPES.contains(&r#type.as_str()) {
+            continue;
+        }
+        match rrsets
+            .iter_mut()
+            .find(|rrset| rrset.subname == subname && rrset.r#type == r#type)
+        {
+            Some(rrset) if rrset.ttl != ttl => {
+                return Err(error(&format!(
+                    "TTL {ttl} differs from TTL {} of the other {type} records of the name",
+                    rrset.ttl,
+                    type = r#type
+                )))
+            }
+            Some(rrset) => rrset.records.push(data),
+            None => rrsets.push(RrsetState {
+                subname,
+                r#type,
+                ttl,
+                records: vec![data],
+            }),
+        }
+    }
+    Ok(rrsets)
+}
+
+// Returns the subname of an owner name relative to the origin.
+fn subname(domain: &str, origin: &str, name: &str) -> Result<String, String> {
+    let name = name.to_ascii_lowercase();
+    let absolute = match name.as_str() {
+        "@" => origin.to_string(),
+        name if name.ends_with('.') => name.to_string(),
+        name => format!("{name}.{origin}"),
+    };
+    let absolute = absolute.trim_end_matches('.');
+    if absolute == domain {
+        return Ok(String::new());
+    }
+    match absolute.strip_suffix(&format!(".{domain}")) {
+        Some(subname) => Ok(subname.to_string()),
+        None => Err(format!("{absolute} is not part of the domain {domain}")),
+    }
+}
+
+// Splits off the next whitespace separated field of the line.
+fn next_field<'a>(line: &mut &'a str) -> Option<&'a str> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let (field, rest) = trimmed
+        .split_once(char::is_whitespace)
+        .unwrap_or((trimmed, ""));
+    *line = rest;
+    Some(field)
+}
+
+// Removes a comment, ignoring semicolons within quoted strings.
+fn strip_comment(line: &str) -> &str {
+    match find_unquoted(line, ';') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+// Returns the index of the first occurrence of the character outside of quoted strings.
+fn find_unquoted(line: &str, needle: char) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == needle && !quoted => return Some(index),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, render, ZoneOptions};
+    use crate::changeset::RrsetState;
+    use desec_api::rrset::ResourceRecordSet;
+
+    fn rrsets() -> Vec<ResourceRecordSet> {
+        let rrset = |subname: &str, r#type: &str, ttl: u64, records: &[&str]| ResourceRecordSet {
+            created: String::new(),
+            domain: String::from("example.com"),
+            subname: Some(subname.to_string()),
+            name: String::new(),
+            rrset_type: r#type.to_string(),
+            ttl,
+            records: records.iter().map(|record| record.to_string()).collect(),
+            touched: String::new(),
+        };
+        vec![
+            rrset("", "A", 3600, &["192.0.2.1"]),
+            rrset(
+                "",
+                "MX",
+                3600,
+                &["10 mail.example.com.", "20 mx.example.net."],
+            ),
+            rrset(
+                "",
+                "TXT",
+                3600,
+                &[
+                    "\"v=spf1 mx -all\"",
+                    "\"a;b \\\"quoted; text\\\"\" \"second\"",
+                    "\"(parentheses) :-)\"",
+                ],
+            ),
+            rrset("*", "A", 300, &["192.0.2.2"]),
+            rrset("*.dev", "CNAME", 300, &["www.example.com."]),
+            rrset("www", "AAAA", 3600, &["2001:db8::1"]),
+            rrset("_dmarc", "TXT", 3600, &["\"v=DMARC1; p=reject\""]),
+        ]
+    }
+
+    fn states(rrsets: &[ResourceRecordSet]) -> Vec<RrsetState> {
+        let mut states: Vec<RrsetState> = rrsets
+            .iter()
+            .map(|rrset| {
+                let mut state = RrsetState::from(rrset);
+                state.records.sort();
+                state
+            })
+            .collect();
+        states.sort_by(|a, b| (&a.subname, &a.r#type).cmp(&(&b.subname, &b.r#type)));
+        states
+    }
+
+    fn sorted(mut parsed: Vec<RrsetState>) -> Vec<RrsetState> {
+        for state in &mut parsed {
+            state.records.sort();
+        }
+        parsed.sort_by(|a, b| (&a.subname, &a.r#type).cmp(&(&b.subname, &b.r#type)));
+        parsed
+    }
+
+    #[test]
+    fn round_trip_relative() {
+        let zone = render("example.com", &rrsets(), &[], ZoneOptions::default());
+        assert!(zone.starts_with("$ORIGIN example.com.\n"));
+        assert!(zone.contains("\n@ "));
+        let parsed = parse("example.com", &zone).unwrap();
+        assert_eq!(sorted(parsed), states(&rrsets()));
+    }
+
+    #[test]
+    fn round_trip_absolute() {
+        let options = ZoneOptions { absolute: true };
+        let zone = render("example.com.", &rrsets(), &[], options);
+        assert!(!zone.contains("$ORIGIN"));
+        assert!(zone.contains("*.dev.example.com."));
+        let parsed = parse("example.com", &zone).unwrap();
+        assert_eq!(sorted(parsed), states(&rrsets()));
+    }
+
+    #[test]
+    fn parse_comments_and_origin() {
+        let zone = "\
+; comment line
+$ORIGIN sub.example.com.
+@      300 IN TXT \"semi;colon\" ; trailing comment
+www    300 in a   192.0.2.1
+other.example.com. 300 IN A 192.0.2.2
+";
+        let parsed = parse("Example.com.", zone).unwrap();
+        let names: Vec<(&str, &str, &[String])> = parsed
+            .iter()
+            .map(|rrset| {
+                (
+                    rrset.subname.as_str(),
+                    rrset.r#type.as_str(),
+                    rrset.records.as_slice(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("sub", "TXT", &["\"semi;colon\"".to_string()][..]),
+                ("www.sub", "A", &["192.0.2.1".to_string()][..]),
+                ("other", "A", &["192.0.2.2".to_string()][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip_blank_owner() {
+        let zone = render("example.com", &rrsets(), &[], ZoneOptions::default());
+        // Replaces repeated owner names by whitespace like other tools write zone files
+        let mut previous = "";
+        let mut blanked = String::new();
+        for line in zone.lines() {
+            let owner = line.split_whitespace().next().unwrap_or("");
+            match owner == previous && !owner.starts_with('$') {
+                true => blanked.push_str(&line.replacen(owner, &" ".repeat(owner.len()), 1)),
+                false => blanked.push_str(line),
+            }
+            blanked.push('\n');
+            previous = owner;
+        }
+        assert!(blanked.contains("\n  "));
+        let parsed = parse("example.com", &blanked).unwrap();
+        assert_eq!(sorted(parsed), states(&rrsets()));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("example.com", "www.example.net. 300 IN A 192.0.2.1").is_err());
+        assert!(parse(
+            "example.com",
+            "www 300 IN A 192.0.2.1\nwww 600 IN A 192.0.2.2"
+        )
+        .is_err());
+        assert!(parse("example.com", "www IN A 192.0.2.1").is_err());
+        assert!(parse("example.com", "$TTL 300").is_err());
+        assert!(parse("example.com", "www 300 IN TXT ( \"a\"\n \"b\" )").is_err());
+        assert!(parse("example.com", "  300 IN A 192.0.2.1").is_err());
+        assert!(parse("example.com", "@ 300 IN DNSKEY 257 3 13 abc")
+            .unwrap()
+            .is_empty());
+    }
+}